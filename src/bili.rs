@@ -16,6 +16,7 @@ static SEASON_ID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#""season_id":\d*"#)
 static EP_URL: Lazy<Url> = Lazy::new(||  Url::parse("https://www.bilibili.com/bangumi/play/").unwrap());
 static MD_URL: Lazy<Url> = Lazy::new(||  Url::parse("https://www.bilibili.com/bangumi/media/").unwrap());
 static PLAYER_URL: Lazy<Url> = Lazy::new(|| Url::parse("https://api.bilibili.com/x/player/v2").unwrap());
+static VIEW_URL: Lazy<Url> = Lazy::new(|| Url::parse("https://api.bilibili.com/x/web-interface/view").unwrap());
 static PAGE_LIST_URL: Lazy<Url> = Lazy::new(|| Url::parse("https://api.bilibili.com/x/player/pagelist").unwrap());
 static SEASON_SECTION_URL: Lazy<Url> = Lazy::new(|| Url::parse("https://api.bilibili.com/pgc/web/season/section").unwrap());
 
@@ -110,6 +111,15 @@ pub struct PageInfo{
    pub duration: u64,
 }
 
+/// the `view` api result, the video with all of its pages.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VideoInfo{
+   pub bvid: String,
+   pub aid: u64,
+   pub title: String,
+   pub pages: Vec<PageInfo>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Episodes{
     pub id: u64,
@@ -126,6 +136,8 @@ pub struct Episodes{
     pub status: Option<u8>,
     pub title: Option<String>,
     pub vid: Option<String>,
+    /// in milliseconds.
+    pub duration: Option<u64>,
 
 }
 
@@ -215,6 +227,18 @@ pub fn get_page_list(bvid: &str)-> Result<Vec<PageInfo>,Box<dyn Error>> {
     Ok(page_list) 
 }
 
+fn handle_video_info_result(content: &str)-> Result<VideoInfo,Box<dyn Error>>{
+    let result: BilibiliResult = serde_json::from_str(content)?;
+
+    let data = result.data()?;
+    Ok(VideoInfo::deserialize(data)?)
+}
+
+pub fn get_video_info(bvid: &str)-> Result<VideoInfo,Box<dyn Error>> {
+    let content= simple_http_get(&VIEW_URL,&vec![("bvid",bvid)])?;
+    handle_video_info_result(&content)
+}
+
 pub fn get_season_episodes(season_id: &u64) -> Result<Vec<Episodes>,Box<dyn Error>> {
     let content= simple_http_get(&SEASON_SECTION_URL,&vec![("season_id",&season_id.to_string())])?;
     let result: BilibiliResult = serde_json::from_str(&content)?;
@@ -283,6 +307,18 @@ mod tests{
         assert_eq!(page.duration,1421);
     }
     #[test]
+    fn handle_video_info_result_test(){
+        let content = r#"{"code":0,"message":"0","ttl":1,"data":{"bvid":"BV1mT42127CQ","aid":1703355805,"videos":1,"title":"【地理】直布罗陀海峡形成与地中海的滔天洪水","duration":794,"pages":[{"cid":1514080023,"page":1,"from":"vupload","part":"直布罗陀","duration":794}]}}"#;
+        let info = bili::handle_video_info_result(content).unwrap();
+
+        assert_eq!(info.bvid,"BV1mT42127CQ");
+        assert_eq!(info.title,"【地理】直布罗陀海峡形成与地中海的滔天洪水");
+        assert_eq!(info.pages[0].cid,1514080023);
+        assert_eq!(info.pages[0].part,"直布罗陀");
+
+        assert!(bili::handle_video_info_result(r#"{"code":-404,"message":"啥都木有"}"#).is_err());
+    }
+    #[test]
     fn get_season_episodes(){
         let test_case: [(u64,u64);13] =  [
             (11931200,19695814),
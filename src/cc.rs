@@ -1,20 +1,734 @@
-use std::io::Error;
+use std::io::{Error,ErrorKind};
 use std::io::Write;
+use std::str::FromStr;
 use regex::{Captures,Regex};
+use serde::{Serialize,Deserialize};
+use once_cell::sync::Lazy;
+use crate::zh::{self,ZhVariant};
 
+static ASS_OVERRIDE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{[^}]*\}").unwrap());
+static ASS_ALIGNMENT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\an([1-9])").unwrap());
+static VTT_ESCAPE_RE: Lazy<Regex> = Lazy::new(|| Regex::new("(&|<|>)").unwrap());
+static VTT_ENTITY_RE: Lazy<Regex> = Lazy::new(|| Regex::new("&(amp|lt|gt|nbsp|lrm|rlm);").unwrap());
+/// `<v Bob>`, `<b>`, `<c.x>`, `<00:00:01.000>` and the like. a literal `<` is always escaped in cue text.
+static VTT_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+const ASS_STYLE_FORMAT: &str = "Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding";
+/// the style used without a bcc style, sized for the default `PlayResY`.
+const ASS_DEFAULT_STYLE: &str = "Default,Arial,20,&H00FFFFFF,&H0000FFFF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,1,1,2,10,10,10,1";
+static SRT_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)</?(?:i|b|u|s|font)(?:\s[^>]*)?>").unwrap());
 
+
+#[derive(Serialize,Deserialize)]
 pub struct CcSubtitle{
     pub name: String,
+    #[serde(default)]
     pub lan: Option<String>,
+    #[serde(default)]
     pub lan_doc: Option<String>,
+    #[serde(default)]
+    pub style: Option<BccStyle>,
+    #[serde(default)]
+    pub meta: Option<VideoMeta>,
     pub lines: Vec<Line>,
 }
 
-#[derive(Debug)]
+impl CcSubtitle{
+    pub fn new(name: &str,lines: Vec<Line>)->Self{
+        CcSubtitle{
+            name: name.to_string(),
+            lan: None,
+            lan_doc: None,
+            style: None,
+            meta: None,
+            lines,
+        }
+    }
+
+    /// move every line by `ms` milliseconds. lines pushed entirely before zero are dropped.
+    pub fn shift(&mut self,ms: i64){
+        let offset = Timestamp::from_millis(ms);
+        for line in self.lines.iter_mut(){
+            line.start = (line.start + offset).max(Timestamp::ZERO);
+            line.end = (line.end + offset).max(Timestamp::ZERO);
+        }
+        self.lines.retain(|line| line.end > Timestamp::ZERO);
+    }
+
+    /// multiply every time by `factor`.
+    pub fn scale(&mut self,factor: f64){
+        for line in self.lines.iter_mut(){
+            line.start = line.start * factor;
+            line.end = line.end * factor;
+        }
+    }
+
+    /// retime subtitles made for a `from` fps video to the same video played at `to` fps.
+    pub fn convert_fps(&mut self,from: f64,to: f64){
+        self.scale(from/to);
+    }
+
+    /// linearly remap every time so that both anchors land on their target times.
+    pub fn resync(&mut self,a: (Anchor,Timestamp),b: (Anchor,Timestamp))-> Result<(),Error>{
+        let (source_a,target_a) = (self.anchor_time(&a.0)?,a.1);
+        let (source_b,target_b) = (self.anchor_time(&b.0)?,b.1);
+        if source_a == source_b{
+            return Err(Error::new(ErrorKind::InvalidInput,"resync anchors must differ"));
+        }
+        let factor = (target_b - target_a).as_millis() as f64 / (source_b - source_a).as_millis() as f64;
+        // anchors moved past each other would turn every cue around.
+        if factor <= 0.{
+            return Err(Error::new(ErrorKind::InvalidInput,"resync anchors must keep their order"));
+        }
+        for line in self.lines.iter_mut(){
+            line.start = (target_a + (line.start - source_a) * factor).max(Timestamp::ZERO);
+            line.end = (target_a + (line.end - source_a) * factor).max(Timestamp::ZERO);
+        }
+        self.lines.retain(|line| line.end > Timestamp::ZERO);
+        Ok(())
+    }
+
+    fn anchor_time(&self,anchor: &Anchor)-> Result<Timestamp,Error>{
+        match anchor{
+            Anchor::Time(time) => Ok(*time),
+            Anchor::Cue(index) => index.checked_sub(1)
+                .and_then(|i| self.lines.get(i))
+                .map(|line| line.start)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput,format!("cue {} not found",index))),
+        }
+    }
+
+    /// clean up cues: sort by start, collapse repeated text, resolve overlaps
+    /// and enforce the minimum duration and gap of `options`.
+    pub fn normalize(&mut self,options: &NormalizeOptions){
+        let mut lines = std::mem::take(&mut self.lines);
+        lines.retain(|line| !line.text().trim().is_empty());
+        lines.sort_by_key(|line| line.start);
+
+        let mut result: Vec<Line> = Vec::with_capacity(lines.len());
+        for line in lines{
+            if let Some(prev) = result.last_mut(){
+                let duplicate = prev.content.trim() == line.content.trim() && prev.secondary == line.secondary;
+                if duplicate && line.start <= prev.end + options.min_gap{
+                    prev.end = prev.end.max(line.end);
+                    continue;
+                }
+                if line.start < prev.end{
+                    // a cue cut shorter than the minimum duration is merged instead.
+                    if options.merge_overlaps || line.start - prev.start < options.min_duration{
+                        prev.append(line);
+                        continue;
+                    }
+                    prev.end = line.start;
+                }
+            }
+            result.push(line);
+        }
+
+        let mut i = 0;
+        while i < result.len(){
+            let Some(limit) = result.get(i+1).map(|next| next.start - options.min_gap) else{
+                let line = &mut result[i];
+                line.end = line.end.max(line.start + options.min_duration);
+                break;
+            };
+            let line = &mut result[i];
+            if limit - line.start >= options.min_duration{
+                // the gap wins over the duration.
+                line.end = line.end.max(line.start + options.min_duration).min(limit);
+            }else if line.start < line.end && line.end <= limit{
+                // a short cue right before the next one keeps what fits.
+                line.end = limit;
+            }else{
+                // no room left before the next cue.
+                let line = result.remove(i);
+                let next = result.remove(i);
+                let mut merged = line;
+                merged.append(next);
+                result.insert(i,merged);
+                continue;
+            }
+            i += 1;
+        }
+        self.lines = result;
+    }
+
+    /// rewrap every line to `options.max_width` columns.
+    /// lines wrapping into more than `options.max_lines` rows are split into several cues, timed by their width.
+    pub fn reflow(&mut self,options: &ReflowOptions){
+        let rewrap = |text: &str| -> Vec<String>{
+            let mut joined = String::new();
+            text.split('\n').map(|x| x.trim()).filter(|x| !x.is_empty()).for_each(|part| join_text(&mut joined,part));
+            wrap_text(&joined,options.max_width.max(1))
+        };
+        let max_lines = options.max_lines.max(1);
+        let mut result = Vec::with_capacity(self.lines.len());
+        for mut line in self.lines.drain(..){
+            let rows = rewrap(&line.content);
+            // bilingual cues stay together.
+            if rows.len() <= max_lines || line.secondary.is_some(){
+                line.content = rows.join("\n");
+                line.secondary = line.secondary.map(|secondary| rewrap(&secondary).join("\n"));
+                result.push(line);
+                continue;
+            }
+            let total = rows.iter().map(|row| text_width(row)).sum::<usize>() as f64;
+            let chunks: Vec<&[String]> = rows.chunks(max_lines).collect();
+            let mut start = line.start;
+            for (i,chunk) in chunks.iter().enumerate(){
+                let width = chunk.iter().map(|row| text_width(row)).sum::<usize>() as f64;
+                let end = if i+1 == chunks.len() { line.end } else { start + (line.end - line.start) * (width / total) };
+                let mut cue = Line::new(start,end,chunk.join("\n"));
+                cue.location = line.location;
+                result.push(cue);
+                start = end;
+            }
+        }
+        self.lines = result;
+    }
+
+    /// convert the text of every line to the chinese `variant` and relabel the language.
+    /// the secondary text of a bilingual line is another language and left untouched.
+    pub fn convert_zh(&mut self,variant: ZhVariant){
+        self.lines.iter_mut().for_each(|line| line.content = zh::convert(&line.content,variant));
+        let (lan,lan_doc) = self.zh_labels(variant);
+        self.lan = Some(lan);
+        self.lan_doc = Some(lan_doc);
+    }
+
+    /// the `lan` and `lan_doc` the subtitle gets from `convert_zh`.
+    pub fn zh_labels(&self,variant: ZhVariant)-> (String,String){
+        let lan_doc = match self.lan_doc.as_deref().and_then(|x| x.split_once('+')){
+            Some((_,secondary)) => format!("{}+{}",variant.lan_doc(),secondary),
+            None => variant.lan_doc().to_string(),
+        };
+        (variant.lan().to_string(),lan_doc)
+    }
+
+    /// report the problems of every cue. `Issue::cue` is 1-based.
+    pub fn check(&self,options: &CheckOptions)-> Vec<Issue>{
+        let mut issues = Vec::new();
+        let mut report = |cue: usize,severity: Severity,message: String| issues.push(Issue{cue: cue+1,severity,message});
+        let duration = self.meta.as_ref().and_then(|meta| meta.duration);
+        // the cue ending last at each position, cues at different positions may overlap.
+        let mut last_end: Vec<(u8,usize,Timestamp)> = Vec::new();
+
+        for (index,line) in self.lines.iter().enumerate(){
+            let times_valid = [line.start,line.end].iter().all(|x| (Timestamp::ZERO..=MAX_TIME).contains(x));
+            if !times_valid{
+                report(index,Severity::Error,format!("invalid time {} --> {}",line.start,line.end));
+                continue;
+            }
+            if line.end < line.start{
+                report(index,Severity::Error,format!("ends at {} before it starts at {}",line.end,line.start));
+            }
+            let chars = line.text().chars().filter(|c| !c.is_whitespace()).count();
+            if chars == 0{
+                report(index,Severity::Warning,String::from("empty content"));
+            }
+            let length = (line.end - line.start).as_secs_f64();
+            if length > 0. && chars as f64 / length > options.max_cps{
+                report(index,Severity::Warning,format!("{:.1} chars per second exceeds {}",chars as f64 / length,options.max_cps));
+            }
+            if let Some(duration) = duration{
+                if line.start >= duration{
+                    report(index,Severity::Error,format!("starts at {} after the video ends at {}",line.start,duration));
+                }else if line.end > duration{
+                    report(index,Severity::Warning,format!("ends at {} after the video ends at {}",line.end,duration));
+                }
+            }
+
+            let position = line.position();
+            match last_end.iter_mut().find(|(p,_,_)| *p == position){
+                Some(last) => {
+                    if line.start < last.2{
+                        report(index,Severity::Warning,format!("overlaps cue {} by {}ms",last.1+1,(last.2 - line.start).as_millis()));
+                    }
+                    if line.end >= last.2{
+                        *last = (position,index,line.end);
+                    }
+                },
+                None => last_end.push((position,index,line.end)),
+            }
+        }
+        issues
+    }
+
+    /// merge another language track into this one.
+    /// every secondary line is attached to the line it overlaps most, lines overlapping nothing are kept on their own.
+    pub fn bilingual(&self,secondary: &CcSubtitle)-> CcSubtitle{
+        let mut attached: Vec<Vec<&str>> = vec![Vec::new();self.lines.len()];
+        let mut lines = Vec::new();
+        for other in secondary.lines.iter(){
+            let best = self.lines.iter()
+                .map(|line| line.end.min(other.end) - line.start.max(other.start))
+                .enumerate()
+                .filter(|(_,overlap)| *overlap > Timestamp::ZERO)
+                .max_by_key(|(_,overlap)| *overlap);
+            match best{
+                Some((index,_)) => attached[index].push(&other.content),
+                None => {
+                    let mut line = Line::new(other.start,other.end,String::new());
+                    line.location = other.location;
+                    line.secondary = Some(other.content.clone());
+                    lines.push(line);
+                },
+            }
+        }
+        for (line,texts) in self.lines.iter().zip(attached){
+            let mut merged = Line::new(line.start,line.end,line.content.clone());
+            merged.location = line.location;
+            if !texts.is_empty(){
+                merged.secondary = Some(texts.join("\n"));
+            }
+            lines.push(merged);
+        }
+        lines.sort_by_key(|line| line.start);
+
+        let doc = |sub: &CcSubtitle| sub.lan_doc.clone().or(sub.lan.clone()).unwrap_or_default();
+        CcSubtitle{
+            name: self.name.clone(),
+            lan: self.lan.clone(),
+            lan_doc: Some(format!("{}+{}",doc(self),doc(secondary))),
+            style: self.style.clone(),
+            meta: self.meta.clone(),
+            lines,
+        }
+    }
+}
+
+/// a point in the source subtitle used by `CcSubtitle::resync`.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Anchor{
+    /// the start of the cue with this 1-based index.
+    Cue(usize),
+    Time(Timestamp),
+}
+
+impl FromStr for Anchor{
+    type Err = String;
+
+    /// `@<index>` for a cue, otherwise a time.
+    fn from_str(s: &str)-> Result<Self,Self::Err>{
+        let s = s.trim();
+        match s.strip_prefix('@'){
+            Some(index) => index.parse::<usize>().map(Anchor::Cue).map_err(|_| format!("invalid cue index {}",s)),
+            None => s.parse::<Timestamp>().map(Anchor::Time),
+        }
+    }
+}
+
+/// options of `CcSubtitle::normalize`.
+#[derive(Debug,Clone)]
+pub struct NormalizeOptions{
+    pub min_duration: Timestamp,
+    pub min_gap: Timestamp,
+    /// merge overlapping cues into one instead of cutting the earlier one short.
+    pub merge_overlaps: bool,
+}
+
+impl Default for NormalizeOptions{
+    fn default()->Self{
+        NormalizeOptions{
+            min_duration: Timestamp::from_millis(700),
+            min_gap: Timestamp::from_millis(80),
+            merge_overlaps: false,
+        }
+    }
+}
+
+/// options of `CcSubtitle::reflow`.
+#[derive(Debug,Clone)]
+pub struct ReflowOptions{
+    /// in display columns, cjk chars count as two.
+    pub max_width: usize,
+    pub max_lines: usize,
+}
+
+/// times beyond a day are treated as broken by `CcSubtitle::check`.
+const MAX_TIME: Timestamp = Timestamp(86_400_000);
+
+/// options of `CcSubtitle::check`.
+#[derive(Debug,Clone)]
+pub struct CheckOptions{
+    /// reading speed above which a cue is reported, whitespace excluded.
+    pub max_cps: f64,
+}
+
+impl Default for CheckOptions{
+    fn default()-> Self{
+        CheckOptions{max_cps: 20.}
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Severity{
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Severity{
+    fn fmt(&self,f: &mut std::fmt::Formatter)-> std::fmt::Result{
+        match self{
+            Severity::Warning => write!(f,"warning"),
+            Severity::Error => write!(f,"error"),
+        }
+    }
+}
+
+/// a problem found by `CcSubtitle::check`.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Issue{
+    pub cue: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// the video a subtitle was downloaded from.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct VideoMeta{
+    pub bvid: String,
+    pub cid: u64,
+    /// page number within the video, starting from 1.
+    #[serde(rename = "page")]
+    pub p: u32,
+    /// duration of the video page, if known.
+    #[serde(default)]
+    pub duration: Option<Timestamp>,
+    /// title of the video, or of the bangumi episode.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// title of the page within the video.
+    #[serde(default)]
+    pub part: Option<String>,
+    /// the page the subtitle was downloaded from, when it is not the video page, e.g. a bangumi episode.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+impl VideoMeta{
+    pub fn new(bvid: &str,cid: u64,p: u32)->Self{
+        VideoMeta{bvid: bvid.to_string(),cid,p,duration: None,title: None,part: None,source: None}
+    }
+
+    /// the page to watch the video the subtitle belongs to.
+    pub fn url(&self)-> String{
+        match &self.source{
+            Some(source) => source.clone(),
+            None => format!("https://www.bilibili.com/video/{}?p={}",self.bvid,self.p),
+        }
+    }
+
+    /// write the video as json, for a sidecar file next to formats without a place for it.
+    /// the same fields as `meta` of the `Json` format, with the resolved `url` added.
+    pub fn write_json(&self,writer: &mut dyn Write)-> Result<(),Error>{
+        let mut json = serde_json::to_value(self)?;
+        json["url"] = self.url().into();
+        serde_json::to_writer_pretty(&mut *writer,&json)?;
+        writeln!(writer)
+    }
+
+    /// `key: value` pairs describing the video, for formats with a place for comments.
+    fn fields(&self)-> Vec<(&'static str,String)>{
+        let mut fields = Vec::new();
+        if let Some(title) = &self.title{
+            fields.push(("Video",title.clone()));
+        }
+        if let Some(part) = &self.part{
+            fields.push(("Part",part.clone()));
+        }
+        fields.push(("Source",self.url()));
+        fields.push(("BVID",self.bvid.clone()));
+        fields.push(("CID",self.cid.to_string()));
+        fields.push(("Page",self.p.to_string()));
+        // a comment is a single line.
+        fields.into_iter().map(|(key,value)| (key,value.replace(['\r','\n']," "))).collect()
+    }
+}
+
+/// the style header of a bcc file.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct BccStyle{
+    pub font_size: f64,
+    pub font_color: String,
+    pub background_alpha: f64,
+    pub background_color: String,
+    pub stroke: String,
+}
+
+impl BccStyle{
+    /// parse `#RRGGBB`.
+    fn rgb(color: &str)-> Option<(u8,u8,u8)>{
+        let hex = color.trim().strip_prefix('#')?;
+        if hex.len() != 6{
+            return None;
+        }
+        let v = u32::from_str_radix(hex,16).ok()?;
+        Some(((v>>16) as u8,(v>>8) as u8,v as u8))
+    }
+
+    /// `Stroke` is either `none` or the outline color.
+    fn stroke_color(&self)-> Option<&str>{
+        Some(self.stroke.as_str()).filter(|stroke| Self::rgb(stroke).is_some())
+    }
+
+    /// font size relative to the bilibili default size 0.4.
+    fn scale(&self)-> f64{
+        self.font_size/0.4
+    }
+}
+
+impl Default for BccStyle{
+    fn default()->Self{
+        BccStyle{
+            font_size: 0.4,
+            font_color: String::from("#FFFFFF"),
+            background_alpha: 0.5,
+            background_color: String::from("#9C27B0"),
+            stroke: String::from("none"),
+        }
+    }
+}
+
+/// a time or duration in whole milliseconds.
+/// negative values are allowed in arithmetic, formatting clamps them to zero.
+/// serialized as the number of milliseconds.
+#[derive(Debug,Clone,Copy,Default,PartialEq,Eq,PartialOrd,Ord,Hash,Serialize,Deserialize)]
+pub struct Timestamp(i64);
+
+impl Timestamp{
+    pub const ZERO: Timestamp = Timestamp(0);
+
+    pub fn from_millis(ms: i64)->Self{
+        Timestamp(ms)
+    }
+
+    /// rounds to the nearest millisecond.
+    pub fn from_secs_f64(seconds: f64)->Self{
+        Timestamp((seconds * 1000.).round() as i64)
+    }
+
+    pub fn as_millis(&self)-> i64{
+        self.0
+    }
+
+    pub fn as_secs_f64(&self)-> f64{
+        self.0 as f64 / 1000.
+    }
+
+    /// round half up to a multiple of `unit` milliseconds.
+    pub fn round_to(&self,unit: i64)-> Timestamp{
+        Timestamp((self.0 + unit/2).div_euclid(unit) * unit)
+    }
+
+    /// hours, minutes, seconds and the fraction of a second with `digits` (0 to 3) decimal digits.
+    /// the time is rounded to the last digit first, negative times are clamped to zero.
+    pub fn clock(&self,digits: u32)-> (u64,u64,u64,u64){
+        let unit = 10_i64.pow(3 - digits.min(3));
+        let ms = self.round_to(unit).0.max(0) as u64;
+        (ms/3_600_000,ms/60_000%60,ms/1000%60,ms%1000/unit as u64)
+    }
+
+    /// parse `hh:mm:ss,ttt`, `hh:mm:ss.ttt`, `mm:ss.ttt` or plain seconds.
+    /// digits beyond milliseconds are rounded.
+    pub fn parse(time: &str)-> Option<Timestamp>{
+        let time = time.trim();
+        let (clock,fraction) = match time.rfind([',','.']){
+            Some(i) => (&time[..i],&time[i+1..]),
+            None => (time,""),
+        };
+        let mut ms: i64 = 0;
+        for part in clock.split(':'){
+            let v = part.trim().parse::<i64>().ok().filter(|x| *x >= 0)?;
+            ms = ms.checked_mul(60)?.checked_add(v)?;
+        }
+        ms = ms.checked_mul(1000)?;
+        if !fraction.chars().all(|c| c.is_ascii_digit()){
+            return None;
+        }
+        let digits = |range: std::ops::Range<usize>| fraction.get(range).unwrap_or("");
+        ms += format!("{:0<3}",digits(0..fraction.len().min(3))).parse::<i64>().ok()?;
+        if digits(3..4) >= "5"{
+            ms += 1;
+        }
+        Some(Timestamp(ms))
+    }
+}
+
+impl FromStr for Timestamp{
+    type Err = String;
+
+    fn from_str(s: &str)-> Result<Self,Self::Err>{
+        Timestamp::parse(s).ok_or(format!("invalid time {}",s))
+    }
+}
+
+/// `hh:mm:ss.ttt`, with a leading `-` when negative.
+impl std::fmt::Display for Timestamp{
+    fn fmt(&self,f: &mut std::fmt::Formatter)-> std::fmt::Result{
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let (hour,minute,second,ms) = Timestamp(self.0.abs()).clock(3);
+        write!(f,"{}{:0>2}:{:0>2}:{:0>2}.{:0>3}",sign,hour,minute,second,ms)
+    }
+}
+
+impl std::ops::Add for Timestamp{
+    type Output = Timestamp;
+
+    fn add(self,other: Timestamp)-> Timestamp{
+        Timestamp(self.0 + other.0)
+    }
+}
+
+impl std::ops::Sub for Timestamp{
+    type Output = Timestamp;
+
+    fn sub(self,other: Timestamp)-> Timestamp{
+        Timestamp(self.0 - other.0)
+    }
+}
+
+/// rounds to the nearest millisecond.
+impl std::ops::Mul<f64> for Timestamp{
+    type Output = Timestamp;
+
+    fn mul(self,factor: f64)-> Timestamp{
+        Timestamp((self.0 as f64 * factor).round() as i64)
+    }
+}
+
+#[derive(Debug,Serialize,Deserialize)]
 pub struct Line{
     pub content: String,
-    pub start: f64,
-    pub end: f64,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    /// bcc `location`, the numpad position of the cue. 2 is bottom center.
+    #[serde(default)]
+    pub location: Option<u8>,
+    /// the text of the second language in bilingual subtitles.
+    #[serde(default)]
+    pub secondary: Option<String>,
+}
+
+impl Line{
+    pub fn new(start: Timestamp,end: Timestamp,content: String)->Self{
+        Line{content,start,end,location: None,secondary: None}
+    }
+
+    /// take over the text and the end of a later `line`.
+    fn append(&mut self,line: Line){
+        self.content = format!("{}\n{}",self.content,line.content);
+        self.secondary = match (self.secondary.take(),line.secondary){
+            (Some(a),Some(b)) => Some(format!("{}\n{}",a,b)),
+            (a,b) => a.or(b),
+        };
+        self.end = self.end.max(line.end);
+    }
+
+    /// the content with the secondary text stacked below it.
+    pub fn text(&self)-> String{
+        match &self.secondary{
+            Some(secondary) if self.content.is_empty() => secondary.clone(),
+            Some(secondary) => format!("{}\n{}",self.content,secondary),
+            None => self.content.clone(),
+        }
+    }
+
+    /// the numpad position of the cue, falls back to bottom center.
+    pub fn position(&self)-> u8{
+        self.location.filter(|x| (1..=9).contains(x)).unwrap_or(2)
+    }
+}
+
+fn invalid_data(msg: String)-> Error{
+    Error::new(ErrorKind::InvalidData,msg)
+}
+
+fn xml_escape(text: &str)-> String{
+    text.replace('&',"&amp;")
+        .replace('<',"&lt;")
+        .replace('>',"&gt;")
+        .replace('"',"&quot;")
+        .replace('\'',"&apos;")
+}
+
+/// append `part` to `text`. cjk text is joined directly, latin words need a space.
+fn join_text(text: &mut String,part: &str){
+    let ascii = text.ends_with(|c: char| c.is_ascii_graphic()) && part.starts_with(|c: char| c.is_ascii_graphic());
+    if ascii{
+        text.push(' ');
+    }
+    text.push_str(part);
+}
+
+/// display width of a char. east asian wide and fullwidth chars count double.
+fn char_width(c: char)-> usize{
+    match c as u32{
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF |
+        0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 |
+        0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+fn text_width(text: &str)-> usize{
+    text.chars().map(char_width).sum()
+}
+
+/// whether the line may break after `chars[index]` because of punctuation. ascii punctuation only counts
+/// at the end of a word, so `1,000` and `3.14` stay whole.
+fn is_break_punctuation(chars: &[char],index: usize)-> bool{
+    match chars[index]{
+        '，'|'。'|'！'|'？'|'、'|'；'|'：'|'…' => true,
+        ','|'.'|'!'|'?'|';'|':' => chars.get(index+1).is_none_or(|c| c.is_whitespace()),
+        _ => false,
+    }
+}
+
+/// greedily wrap `text` into lines no wider than `width`.
+/// breaks after punctuation if that keeps at least half of the line, otherwise at a space or next to a cjk char.
+fn wrap_text(text: &str,width: usize)-> Vec<String>{
+    let chars: Vec<char> = text.chars().collect();
+    let mut lines = Vec::new();
+    let mut start = 0;
+    loop{
+        while start < chars.len() && chars[start].is_whitespace(){
+            start += 1;
+        }
+        if start >= chars.len(){
+            break;
+        }
+        let mut end = start;
+        let mut current = 0;
+        while end < chars.len() && current + char_width(chars[end]) <= width{
+            current += char_width(chars[end]);
+            end += 1;
+        }
+        if end == chars.len(){
+            lines.push(chars[start..end].iter().collect::<String>());
+            break;
+        }
+        if end == start{
+            // a single char wider than the line.
+            end += 1;
+        }else{
+            let half = start + (end - start).div_ceil(2);
+            let mut candidates = (start+1..=end).rev();
+            let punctuation = candidates.clone().find(|&b| b >= half && is_break_punctuation(&chars,b-1));
+            let space = candidates.clone().find(|&b| chars[b].is_whitespace());
+            let cjk = candidates.find(|&b| char_width(chars[b-1]) == 2 || char_width(chars[b]) == 2);
+            end = punctuation.or(space).or(cjk).unwrap_or(end);
+        }
+        lines.push(chars[start..end].iter().collect::<String>().trim_end().to_string());
+        start = end;
+    }
+    lines
+}
+
+/// parse `start --> end`. anything after the end time (srt coordinates, vtt cue settings) is ignored.
+fn parse_cue_timing(line: &str)-> Option<(Timestamp,Timestamp)>{
+    let (start,end) = line.split_once("-->")?;
+    let end = end.split_whitespace().next()?;
+    Some((Timestamp::parse(start)?,Timestamp::parse(end)?))
 }
 
 pub trait Formatter{
@@ -33,14 +747,57 @@ impl Srt{
         Srt{}
     }
 
-    fn format_time(time: f64)-> String{
-        let hour = time as u64 /3600;
-        let minute = time as u64 /60 %60;
-        let second = time as u64 %60;
-        let ms = time.fract().mul_add(1000.,0.)as u64;
+    fn format_time(time: Timestamp)-> String{
+        let (hour,minute,second,ms) = time.clock(3);
         format!("{:0>2}:{:0>2}:{:0>2},{:0>3}",hour,minute,second ,ms)
 
     }
+
+    /// parse srt content. tolerant of BOMs, CRLF, missing indices and `.` as millisecond separator.
+    /// `<i>`, `<b>`, `<u>`, `<s>` and `<font>` tags are dropped, and a stray row that is neither an index
+    /// nor a timing, e.g. text after a blank line inside a cue, is joined to the previous cue.
+    pub fn parse(name: &str, content: &str)-> Result<CcSubtitle,Error>{
+        let content = content.trim_start_matches('\u{feff}');
+        let mut lines: Vec<Line> = Vec::new();
+        let mut rows = content.lines().map(|x| x.trim_end()).peekable();
+        while let Some(row) = rows.next(){
+            let row = row.trim();
+            if row.is_empty(){
+                continue;
+            }
+            let timing = match parse_cue_timing(row){
+                Some(timing) => timing,
+                // index line
+                None => match rows.peek().and_then(|next| parse_cue_timing(next)){
+                    Some(timing) => {
+                        rows.next();
+                        timing
+                    },
+                    None => {
+                        if let Some(last) = lines.last_mut(){
+                            last.content.push('\n');
+                            last.content.push_str(&SRT_TAG_RE.replace_all(row,""));
+                        }
+                        continue;
+                    },
+                },
+            };
+            let mut text: Vec<&str> = Vec::new();
+            while let Some(next) = rows.peek(){
+                if next.trim().is_empty() || parse_cue_timing(next).is_some(){
+                    break;
+                }
+                text.push(next);
+                rows.next();
+            }
+            let content = SRT_TAG_RE.replace_all(&text.join("\n"),"").into_owned();
+            lines.push(Line::new(timing.0,timing.1,content));
+        }
+        if lines.is_empty(){
+            return Err(invalid_data("no srt cue found".to_string()));
+        }
+        Ok(CcSubtitle::new(name,lines))
+    }
 }
 
 impl Formatter for Srt{
@@ -51,10 +808,18 @@ impl Formatter for Srt{
     }
 
     fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        // white is what players draw anyway.
+        let color = subtitle.style.as_ref()
+            .map(|style| &style.font_color)
+            .filter(|color| !color.eq_ignore_ascii_case("#FFFFFF"));
         for (index, line ) in subtitle.lines.iter().enumerate(){
+            let content = match color{
+                Some(color) => format!("<font color=\"{}\">{}</font>",color,line.text()),
+                None => line.text(),
+            };
             let str=format!("{}\n\
                 {} --> {}\n\
-                {}\n\n",index+1,Self::format_time(line.start),Self::format_time(line.end),line.content
+                {}\n\n",index+1,Self::format_time(line.start),Self::format_time(line.end),content
                 );
 
             writer.write(str.as_bytes())?;
@@ -67,17 +832,17 @@ impl Formatter for Srt{
 }
 
 pub struct Sub{
-
+    fps: f64,
 }
 impl Sub{
     
-    pub fn new()->Self{
-        Sub{}
+    pub fn new(fps: f64)->Self{
+        Sub{fps}
     }
 
 
-    fn to_frame(second: f64)-> u64{
-        (second * 23.976) as u64
+    fn to_frame(&self,time: Timestamp)-> u64{
+        (time.as_secs_f64() * self.fps).round().max(0.) as u64
 
     }
 }
@@ -92,13 +857,15 @@ impl Formatter for Sub{
     }
 
     fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        // lets players detect the frame rate.
+        writeln!(writer,"{{1}}{{1}}{}",self.fps)?;
         for  line  in subtitle.lines.iter(){
             let str=format!(
-                "{{{}}}{{{}}}{}\n\n",
-                Self::to_frame(line.start),Sub::to_frame(line.end),line.content
+                "{{{}}}{{{}}}{}\n",
+                self.to_frame(line.start),self.to_frame(line.end),line.text().replace('\n',"|")
                 );
 
-            writer.write(str.as_bytes())?;
+            writer.write_all(str.as_bytes())?;
         }
         Ok(())
     }
@@ -106,39 +873,270 @@ impl Formatter for Sub{
 }
 
 pub struct Ass{
+    template: Option<String>,
+    play_res: Option<(u32,u32)>,
 }
 impl Ass{
     pub fn new()->Self{
-        Ass{}
+        Ass{template: None,play_res: None}
+    }
+
+    /// replace the generated `[Script Info]` and `[V4+ Styles]` sections.
+    /// anything from `[Events]` on is ignored, so a whole ass file can be used as template.
+    pub fn template(mut self,template: String)->Self{
+        self.template = Some(template);
+        self
+    }
+
+    pub fn play_res(mut self,x: u32,y: u32)->Self{
+        self.play_res = Some((x,y));
+        self
     }
 
-    fn format_time(time: f64)-> String{
-        let hour = time as u64 /3600;
-        let minute = time as u64 /60 %60;
-        let second = time as u64 %60;
-        let hunderdths = (time.fract() * 100.) as u64; 
+    /// the template header with `PlayResX`/`PlayResY` replaced when `play_res` is set.
+    /// a `Secondary` style derived from the template's `Default` one is added when `secondary`
+    /// is set and the template has none.
+    fn format_template(&self,template: &str,meta: Option<&VideoMeta>,secondary: bool)-> String{
+        let template = template.trim_start_matches('\u{feff}');
+        let mut header = String::new();
+        let mut in_script_info = false;
+        let mut in_styles = false;
+        let mut format: Vec<String> = ASS_STYLE_FORMAT.split(',').map(|x| x.trim().to_string()).collect();
+        let mut base: Option<String> = None;
+        let mut missing_secondary = secondary;
+        for row in template.lines(){
+            let trimmed = row.trim();
+            if trimmed.starts_with('['){
+                if in_styles && missing_secondary{
+                    Self::push_style(&mut header,&Self::secondary_style(&format,base.as_deref()));
+                    missing_secondary = false;
+                }
+                if trimmed.eq_ignore_ascii_case("[events]"){
+                    in_styles = false;
+                    break;
+                }
+                in_script_info = trimmed.eq_ignore_ascii_case("[script info]");
+                in_styles = trimmed.eq_ignore_ascii_case("[v4+ styles]") || trimmed.eq_ignore_ascii_case("[v4 styles]");
+                header.push_str(row);
+                header.push('\n');
+                if let (true,Some((x,y))) = (in_script_info,self.play_res){
+                    header.push_str(&format!("PlayResX: {}\nPlayResY: {}\n",x,y));
+                }
+                if let (true,Some(meta)) = (in_script_info,meta){
+                    header.push_str(&Self::format_meta(meta));
+                }
+                continue;
+            }
+            if in_script_info && self.play_res.is_some() && (trimmed.starts_with("PlayResX:") || trimmed.starts_with("PlayResY:")){
+                continue;
+            }
+            if in_styles{
+                if let Some(fields) = trimmed.strip_prefix("Format:"){
+                    format = fields.split(',').map(|x| x.trim().to_string()).collect();
+                }else if let Some(fields) = trimmed.strip_prefix("Style:"){
+                    let name = fields.split(',').next().unwrap_or("").trim();
+                    if name.eq_ignore_ascii_case("Secondary"){
+                        missing_secondary = false;
+                    }
+                    if base.is_none() || name == "Default"{
+                        base = Some(fields.trim().to_string());
+                    }
+                }
+            }
+            header.push_str(row);
+            header.push('\n');
+        }
+        if missing_secondary{
+            if !in_styles{
+                Self::push_style(&mut header,&format!("\n[V4+ Styles]\nFormat: {}",ASS_STYLE_FORMAT));
+            }
+            Self::push_style(&mut header,&Self::secondary_style(&format,base.as_deref()));
+        }
+        let header = header.trim_end();
+        format!("{}\n\n",header)
+    }
+
+    /// append rows to the header before the blank lines closing its last section.
+    fn push_style(header: &mut String,rows: &str){
+        header.truncate(header.trim_end().len());
+        header.push('\n');
+        header.push_str(rows);
+        header.push_str("\n\n");
+    }
+
+    /// `base` renamed to `Secondary` with three quarters of its font size.
+    /// without a base the generated default style is used.
+    fn secondary_style(format: &[String],base: Option<&str>)-> String{
+        let default_format: Vec<String>;
+        let (format,base) = match base{
+            Some(base) => (format,base),
+            None => {
+                default_format = ASS_STYLE_FORMAT.split(',').map(|x| x.trim().to_string()).collect();
+                (default_format.as_slice(),ASS_DEFAULT_STYLE)
+            },
+        };
+        let mut fields: Vec<String> = base.split(',').map(|x| x.trim().to_string()).collect();
+        let index = |name: &str| format.iter().position(|x| x.eq_ignore_ascii_case(name));
+        if let Some(field) = index("Name").and_then(|i| fields.get_mut(i)){
+            *field = String::from("Secondary");
+        }
+        if let Some(field) = index("Fontsize").and_then(|i| fields.get_mut(i)){
+            if let Ok(size) = field.parse::<f64>(){
+                *field = (size * 0.75).round().to_string();
+            }
+        }
+        format!("Style: {}",fields.join(","))
+    }
+
+    /// the video as `[Script Info]` comments.
+    fn format_meta(meta: &VideoMeta)-> String{
+        meta.fields().iter().map(|(key,value)| format!("; {}: {}\n",key,value)).collect()
+    }
+
+    /// the default font size 20 is meant for the default `PlayResY` 288.
+    fn font_size(&self,scale: f64)-> f64{
+        let play_res_y = self.play_res.map(|(_,y)| y).unwrap_or(288);
+        (20. * scale * play_res_y as f64 / 288.).round()
+    }
+
+    /// ass only has centiseconds.
+    fn format_time(time: Timestamp)-> String{
+        let (hour,minute,second,hunderdths) = time.clock(2);
         format!("{}:{:0>2}:{:0>2}.{:0>2}",hour,minute,second,hunderdths)
     }
     
     fn format_line(line: &Line) -> String{
-       format!("Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
-            Ass::format_time(line.start),Ass::format_time(line.end),line.content.replace("\n","\\n"))
+       // the default style is already bottom center.
+       let alignment = match line.position(){
+           2 => String::new(),
+           an => format!("{{\\an{}}}",an),
+       };
+       // `\N` breaks whatever the WrapStyle of a template is, `\n` only with WrapStyle 2.
+       let mut text = line.content.replace("\n","\\N");
+       if let Some(secondary) = &line.secondary{
+           if !text.is_empty(){
+               text.push_str("\\N");
+           }
+           text.push_str("{\\rSecondary}");
+           text.push_str(&secondary.replace("\n","\\N"));
+       }
+       format!("Dialogue: 0,{},{},Default,,0,0,0,,{}{}\n",
+            Ass::format_time(line.start),Ass::format_time(line.end),alignment,text)
         
     }
+
+    /// strip override blocks and turn ass escapes into plain text.
+    fn parse_text(text: &str)-> String{
+        let text = ASS_OVERRIDE_RE.replace_all(text,"");
+        text.replace("\\N","\n")
+            .replace("\\n","\n")
+            .replace("\\h"," ")
+    }
+
+    /// parse ass/ssa content. only `Dialogue` events in the `[Events]` section are imported.
+    pub fn parse(name: &str, content: &str)-> Result<CcSubtitle,Error>{
+        let content = content.trim_start_matches('\u{feff}');
+        let mut in_events = false;
+        let mut format: Vec<String> = "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text"
+            .split(',').map(|x| x.trim().to_lowercase()).collect();
+        let mut lines = Vec::new();
+        for row in content.lines(){
+            let row = row.trim();
+            if row.starts_with('['){
+                in_events = row.eq_ignore_ascii_case("[events]");
+                continue;
+            }
+            if !in_events{
+                continue;
+            }
+            let Some((key,value)) = row.split_once(':') else{
+                continue;
+            };
+            match key.trim(){
+                "Format" => {
+                    format = value.split(',').map(|x| x.trim().to_lowercase()).collect();
+                },
+                "Dialogue" => {
+                    let fields: Vec<&str> = value.trim_start().splitn(format.len(),',').collect();
+                    let field = |key: &str| format.iter().position(|x| x==key).and_then(|i| fields.get(i));
+                    let start = field("start").and_then(|x| Timestamp::parse(x));
+                    let end = field("end").and_then(|x| Timestamp::parse(x));
+                    let text = field("text");
+                    match (start,end,text){
+                        (Some(start),Some(end),Some(text)) => {
+                            let mut line = Line::new(start,end,Self::parse_text(text));
+                            line.location = ASS_ALIGNMENT_RE.captures(text)
+                                .and_then(|cap| cap[1].parse::<u8>().ok());
+                            lines.push(line);
+                        },
+                        _ => return Err(invalid_data(format!("invalid ass event '{}'",row))),
+                    }
+                },
+                _ => {},
+            }
+        }
+        if lines.is_empty(){
+            return Err(invalid_data("no ass dialogue found".to_string()));
+        }
+        lines.sort_by_key(|line| line.start);
+        Ok(CcSubtitle::new(name,lines))
+    }
+    /// `&HAABBGGRR`. ass alpha counts transparency, 00 is opaque.
+    fn format_color(color: &str,opacity: f64)-> String{
+        let (r,g,b) = BccStyle::rgb(color).unwrap_or((255,255,255));
+        let alpha = ((1. - opacity.clamp(0.,1.)) * 255.).round() as u8;
+        format!("&H{:0>2X}{:0>2X}{:0>2X}{:0>2X}",alpha,b,g,r)
+    }
+
+    /// the `Default` style translated from the bcc header.
+    fn format_style(&self,style: &BccStyle)-> String{
+        let font_size = self.font_size(style.scale());
+        let primary = Self::format_color(&style.font_color,1.);
+        // a visible background is drawn as an opaque box.
+        let (border_style,outline) = if style.background_alpha > 0.{
+            (3,Self::format_color(&style.background_color,style.background_alpha))
+        }else{
+            (1,Self::format_color(style.stroke_color().unwrap_or("#000000"),1.))
+        };
+        format!("Style: Default,Arial,{},{},&H0000FFFF,{},{},0,0,0,0,100,100,0,0,{},1,0,2,10,10,10,1\n",
+            font_size,primary,outline,outline,border_style)
+    }
+
     fn write_header(&mut self,writer: &mut dyn Write,subtitle:&CcSubtitle) -> Result<(),Error>{
+        if let Some(template) = &self.template{
+            let secondary = subtitle.lines.iter().any(|line| line.secondary.is_some());
+            writer.write_all(self.format_template(template,subtitle.meta.as_ref(),secondary).as_bytes())?;
+            return Ok(());
+        }
         
         writer.write(b"[Script Info]\n")?;
         writer.write(b"; Generated by bccdc.\n")?;
         writer.write(format!("Title: {}\n",subtitle.name).as_bytes())?;
+        if let Some(meta) = &subtitle.meta{
+            writer.write_all(Self::format_meta(meta).as_bytes())?;
+        }
         writer.write(b"ScriptType: v4.00+\n")?;
         writer.write(b"WrapStyle: 2\n")?;
+        if let Some((x,y)) = self.play_res{
+            writeln!(writer,"PlayResX: {}\nPlayResY: {}",x,y)?;
+        }
 
         writer.write(b"\n")?;
 
         writer.write(b"[V4+ Styles]\n")?;
-        writer.write(b"Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n")?;
+        writeln!(writer,"Format: {}",ASS_STYLE_FORMAT)?;
         
-        writer.write(b"Style: Default,Arial,20,&H00FFFFFF,&H0000FFFF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,1,1,2,10,10,10,1\n")?;
+        let default_style = match &subtitle.style{
+            Some(style) => self.format_style(style),
+            None => format!("Style: {}\n",ASS_DEFAULT_STYLE.replacen(",20,",&format!(",{},",self.font_size(1.)),1)),
+        };
+        writer.write_all(default_style.as_bytes())?;
+        if subtitle.lines.iter().any(|line| line.secondary.is_some()){
+            // the second language is drawn smaller, in the same look as the primary one.
+            let font_size = self.font_size(subtitle.style.as_ref().map(|style| style.scale()).unwrap_or(1.) * 0.75);
+            let fields: Vec<&str> = default_style.trim_end().split(',').collect();
+            writeln!(writer,"Style: Secondary,{},{},{}",fields[1],font_size,fields[3..].join(","))?;
+        }
         writer.write(b"\n")?;
         Ok(())
     }
@@ -172,18 +1170,58 @@ impl Vtt{
         Vtt{}
     }
 
-    pub fn format_time(time: f64)-> String{
-        let hour = time as u64 /3600;
-        let minute = time as u64 /60 %60;
-        let second = time as u64 %60;
-        let ms = time.fract().mul_add(1000.,0.)as u64;
+    pub fn format_time(time: Timestamp)-> String{
+        let (hour,minute,second,ms) = time.clock(3);
         format!("{:0>2}:{:0>2}:{:0>2}.{:0>3}",hour,minute,second ,ms)
     }
 
+    fn write_style(writer: &mut dyn Write,style: &BccStyle) -> Result<(),Error>{
+        let (r,g,b) = BccStyle::rgb(&style.background_color).unwrap_or((0,0,0));
+        writeln!(writer,"STYLE")?;
+        writeln!(writer,"::cue {{")?;
+        writeln!(writer,"  color: {};",style.font_color)?;
+        writeln!(writer,"  background-color: rgba({},{},{},{});",r,g,b,style.background_alpha)?;
+        writeln!(writer,"  font-size: {}%;",(style.scale() * 100.).round())?;
+        if let Some(stroke) = style.stroke_color(){
+            writeln!(writer,"  text-shadow: {0} 1px 0 0, {0} -1px 0 0, {0} 0 1px 0, {0} 0 -1px 0;",stroke)?;
+        }
+        writeln!(writer,"}}")?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// a NOTE block describing the video. a note must not contain `-->`.
+    fn write_meta(writer: &mut dyn Write,meta: &VideoMeta) -> Result<(),Error>{
+        writeln!(writer,"NOTE")?;
+        for (key,value) in meta.fields(){
+            writeln!(writer,"{}: {}",key,value.replace("-->","->"))?;
+        }
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// cue settings placing the cue at its numpad position.
+    fn cue_settings(line: &Line)-> String{
+        let position = line.position();
+        let mut settings = String::new();
+        match (position-1)/3{
+            1 => settings.push_str(" line:50%"),
+            2 => settings.push_str(" line:10%"),
+            _ => {},
+        }
+        match (position-1)%3{
+            0 => settings.push_str(" position:10% align:left"),
+            2 => settings.push_str(" position:90% align:right"),
+            _ => {},
+        }
+        settings
+    }
+
     fn write_line(writer: &mut dyn Write,line: &Line) -> Result<(),Error>{
-        writer.write(format!("{} --> {}\n",Self::format_time(line.start),Self::format_time(line.end)).as_bytes())?;
-        let s = Regex::new("(&|<|>)").unwrap()
-            .replace_all(&line.content,|cap: &Captures|{
+        writer.write(format!("{} --> {}{}\n",Self::format_time(line.start),Self::format_time(line.end),Self::cue_settings(line)).as_bytes())?;
+        let text = line.text();
+        let s = VTT_ESCAPE_RE
+            .replace_all(&text,|cap: &Captures|{
                 match &cap[0]{
                     "&"=> "&amp;",
                     "<"=> "&lt;",
@@ -195,6 +1233,101 @@ impl Vtt{
         writer.write(b"\n\n")?;
         Ok(())
     }
+
+    /// the inverse of the escaping done by `write_line`. cue text tags are dropped.
+    fn unescape(text: &str)-> String{
+        VTT_ENTITY_RE
+            .replace_all(&VTT_TAG_RE.replace_all(text,""),|cap: &Captures|{
+                match &cap[1]{
+                    "amp"=> "&",
+                    "lt"=> "<",
+                    "gt"=> ">",
+                    "nbsp"=> "\u{a0}",
+                    "lrm"=> "\u{200e}",
+                    "rlm"=> "\u{200f}",
+                    _=> panic!("vtt"),
+                }
+            }).into_owned()
+    }
+
+    /// the numpad position described by the `line`, `position` and `align` cue settings,
+    /// the inverse of `cue_settings`. `None` without any of them.
+    fn parse_cue_settings(settings: &str)-> Option<u8>{
+        let percent = |value: &str| value.strip_suffix('%').and_then(|x| x.parse::<f64>().ok());
+        let mut row = None;
+        let mut column = None;
+        let mut align = None;
+        for setting in settings.split_whitespace(){
+            let Some((key,value)) = setting.split_once(':') else{
+                continue;
+            };
+            // `line:50%,center` and `position:10%,line-left` carry an alignment after the comma.
+            let value = value.split(',').next().unwrap_or(value);
+            match key{
+                "line" => row = match percent(value){
+                    Some(x) if x < 100./3. => Some(2),
+                    Some(x) if x < 200./3. => Some(1),
+                    Some(_) => Some(0),
+                    // a line number counts from the top, a negative one from the bottom.
+                    None => value.parse::<i32>().ok().map(|x| if x < 0 { 0 } else { 2 }),
+                },
+                "position" => column = match percent(value){
+                    Some(x) if x < 100./3. => Some(0),
+                    Some(x) if x > 200./3. => Some(2),
+                    Some(_) => Some(1),
+                    None => None,
+                },
+                "align" => align = match value{
+                    "left"|"start" => Some(0),
+                    "right"|"end" => Some(2),
+                    "center"|"middle" => Some(1),
+                    _ => None,
+                },
+                _ => {},
+            }
+        }
+        let column = column.or(align);
+        if row.is_none() && column.is_none(){
+            return None;
+        }
+        Some(row.unwrap_or(0)*3 + column.unwrap_or(1) + 1)
+    }
+
+    /// parse webvtt content. NOTE/STYLE/REGION blocks and cue identifiers are skipped,
+    /// cue settings become the location of the line.
+    pub fn parse(name: &str, content: &str)-> Result<CcSubtitle,Error>{
+        let content = content.trim_start_matches('\u{feff}').replace("\r\n","\n");
+        let mut blocks = content.split("\n\n")
+            .map(|x| x.trim_matches('\n'))
+            .filter(|x| !x.is_empty());
+
+        match blocks.next(){
+            Some(header) if header.starts_with("WEBVTT") => {},
+            _ => return Err(invalid_data("missing WEBVTT header".to_string())),
+        }
+
+        let mut lines = Vec::new();
+        for block in blocks{
+            if block.starts_with("NOTE") || block.starts_with("STYLE") || block.starts_with("REGION"){
+                continue;
+            }
+            let mut rows = block.lines();
+            let (timing,row) = rows.by_ref()
+                .take(2)
+                .find_map(|row| Some((parse_cue_timing(row)?,row)))
+                .ok_or_else(|| invalid_data(format!("invalid vtt cue '{}'",block)))?;
+            let text = rows.map(Self::unescape).collect::<Vec<String>>().join("\n");
+            let mut line = Line::new(timing.0,timing.1,text);
+            // everything after the end time.
+            let settings = row.split_once("-->").map_or("",|(_,end)| end.trim_start());
+            line.location = Self::parse_cue_settings(settings.split_once(char::is_whitespace).map_or("",|(_,x)| x));
+            lines.push(line);
+        }
+        if lines.is_empty(){
+            return Err(invalid_data("no vtt cue found".to_string()));
+        }
+        Ok(CcSubtitle::new(name,lines))
+    }
     
 }
 
@@ -205,6 +1338,12 @@ impl Formatter for Vtt{
 
     fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
         writer.write(b"WEBVTT\n\n")?;
+        if let Some(meta) = &subtitle.meta{
+            Self::write_meta(writer,meta)?;
+        }
+        if let Some(style) = &subtitle.style{
+            Self::write_style(writer,style)?;
+        }
 
         for line in subtitle.lines.iter(){
             Self::write_line(writer,line)?;
@@ -213,3 +1352,988 @@ impl Formatter for Vtt{
     }
 
 }
+
+/// how `Ttml` writes `begin`/`end`.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum TtmlTime{
+    /// clock time `hh:mm:ss.mmm`.
+    Clock,
+    /// tick time `<n>t` with the given `ttp:tickRate`.
+    Ticks(u64),
+}
+
+/// ttml following the imsc1 text profile.
+pub struct Ttml{
+    time: TtmlTime,
+}
+
+impl Ttml{
+    pub fn new(time: TtmlTime)->Self{
+        Ttml{time}
+    }
+
+    fn format_time(&self,time: Timestamp)-> String{
+        match self.time{
+            TtmlTime::Clock => {
+                let (hour,minute,second,ms) = time.clock(3);
+                format!("{:0>2}:{:0>2}:{:0>2}.{:0>3}",hour,minute,second,ms)
+            },
+            TtmlTime::Ticks(rate) => format!("{}t",time.as_millis().max(0) as u128 * rate as u128 / 1000),
+        }
+    }
+
+    /// bcp47 tag for `xml:lang`. bilibili prefixes ai generated tracks with `ai-`.
+    fn lang(subtitle: &CcSubtitle)-> String{
+        subtitle.lan.as_deref()
+            .map(|lan| lan.strip_prefix("ai-").unwrap_or(lan))
+            .unwrap_or("")
+            .to_string()
+    }
+}
+
+impl Formatter for Ttml{
+    fn ext(&self)->&str{
+        "ttml"
+    }
+
+    fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        writeln!(writer,"<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        write!(writer,"<tt xmlns=\"http://www.w3.org/ns/ttml\" \
+            xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+            xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" \
+            xmlns:ttm=\"http://www.w3.org/ns/ttml#metadata\" \
+            ttp:profile=\"http://www.w3.org/ns/ttml/profile/imsc1/text\" \
+            ttp:timeBase=\"media\"")?;
+        if let TtmlTime::Ticks(rate) = self.time{
+            write!(writer," ttp:tickRate=\"{}\"",rate)?;
+        }
+        writeln!(writer," xml:lang=\"{}\">",xml_escape(&Self::lang(subtitle)))?;
+
+        writeln!(writer,"  <head>")?;
+        writeln!(writer,"    <metadata>")?;
+        writeln!(writer,"      <ttm:title>{}</ttm:title>",xml_escape(&subtitle.name))?;
+        writeln!(writer,"    </metadata>")?;
+        writeln!(writer,"    <styling>")?;
+        let (color,background,font_size) = match &subtitle.style{
+            Some(style) => {
+                let (r,g,b) = BccStyle::rgb(&style.background_color).unwrap_or((0,0,0));
+                let alpha = (style.background_alpha.clamp(0.,1.) * 255.).round() as u8;
+                (style.font_color.clone(),format!("#{:0>2x}{:0>2x}{:0>2x}{:0>2x}",r,g,b,alpha),(style.scale() * 100.).round())
+            },
+            None => (String::from("white"),String::from("transparent"),100.),
+        };
+        writeln!(writer,"      <style xml:id=\"default\" tts:color=\"{}\" tts:backgroundColor=\"{}\" tts:fontFamily=\"proportionalSansSerif\" tts:fontSize=\"{}%\" tts:textAlign=\"center\" tts:textOutline=\"black 5%\"/>",
+            xml_escape(&color),background,font_size)?;
+        writeln!(writer,"    </styling>")?;
+        writeln!(writer,"    <layout>")?;
+        writeln!(writer,"      <region xml:id=\"bottom\" tts:origin=\"10% 10%\" tts:extent=\"80% 80%\" tts:displayAlign=\"after\"/>")?;
+        writeln!(writer,"    </layout>")?;
+        writeln!(writer,"  </head>")?;
+
+        writeln!(writer,"  <body style=\"default\" region=\"bottom\">")?;
+        writeln!(writer,"    <div>")?;
+        for line in subtitle.lines.iter(){
+            let content = line.text().split('\n')
+                .map(xml_escape)
+                .collect::<Vec<String>>()
+                .join("<br/>");
+            writeln!(writer,"      <p begin=\"{}\" end=\"{}\">{}</p>",self.format_time(line.start),self.format_time(line.end),content)?;
+        }
+        writeln!(writer,"    </div>")?;
+        writeln!(writer,"  </body>")?;
+        writeln!(writer,"</tt>")?;
+        Ok(())
+    }
+
+}
+
+/// youtube sbv.
+#[derive(Default)]
+pub struct Sbv{
+}
+
+impl Sbv{
+    pub fn new()->Self{
+        Sbv{}
+    }
+
+    fn format_time(time: Timestamp)-> String{
+        let (hour,minute,second,ms) = time.clock(3);
+        format!("{}:{:0>2}:{:0>2}.{:0>3}",hour,minute,second,ms)
+    }
+}
+
+impl Formatter for Sbv{
+    fn ext(&self)->&str{
+        "sbv"
+    }
+
+    fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        for line in subtitle.lines.iter(){
+            write!(writer,"{},{}\n{}\n\n",Self::format_time(line.start),Self::format_time(line.end),line.text())?;
+        }
+        Ok(())
+    }
+
+}
+
+/// youtube timed text format 3.
+#[derive(Default)]
+pub struct Srv3{
+}
+
+impl Srv3{
+    pub fn new()->Self{
+        Srv3{}
+    }
+}
+
+impl Formatter for Srv3{
+    fn ext(&self)->&str{
+        "srv3"
+    }
+
+    fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        writeln!(writer,"<?xml version=\"1.0\" encoding=\"utf-8\"?>")?;
+        writeln!(writer,"<timedtext format=\"3\">")?;
+        writeln!(writer,"<body>")?;
+        for line in subtitle.lines.iter(){
+            let start = line.start.max(Timestamp::ZERO);
+            let duration = (line.end - start).max(Timestamp::ZERO);
+            writeln!(writer,"<p t=\"{}\" d=\"{}\">{}</p>",start.as_millis(),duration.as_millis(),xml_escape(&line.text()))?;
+        }
+        writeln!(writer,"</body>")?;
+        writeln!(writer,"</timedtext>")?;
+        Ok(())
+    }
+
+}
+
+/// lrc lyrics.
+pub struct Lrc{
+    end_tags: bool,
+}
+
+impl Lrc{
+    /// `end_tags` appends an enhanced lrc `<mm:ss.xx>` tag marking where each line ends.
+    pub fn new(end_tags: bool)->Self{
+        Lrc{end_tags}
+    }
+
+    /// minutes keep counting past an hour.
+    fn format_time(time: Timestamp)-> String{
+        let (hour,minute,second,cs) = time.clock(2);
+        format!("{:0>2}:{:0>2}.{:0>2}",hour*60+minute,second,cs)
+    }
+}
+
+impl Formatter for Lrc{
+    fn ext(&self)->&str{
+        "lrc"
+    }
+
+    fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        // names like `artist - title` fill both headers.
+        match subtitle.name.split_once(" - "){
+            Some((artist,title)) => {
+                writeln!(writer,"[ti:{}]",title.trim())?;
+                writeln!(writer,"[ar:{}]",artist.trim())?;
+            },
+            None => writeln!(writer,"[ti:{}]",subtitle.name)?,
+        }
+        writeln!(writer,"[re:bccdc]")?;
+
+        for line in subtitle.lines.iter(){
+            let content = line.text().split('\n').map(|x| x.trim()).collect::<Vec<&str>>().join(" ");
+            write!(writer,"[{}]{}",Self::format_time(line.start),content)?;
+            if self.end_tags{
+                write!(writer," <{}>",Self::format_time(line.end))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+}
+
+/// join consecutive lines into paragraphs, breaking on gaps longer than `gap` seconds.
+/// returns the start time and text of every paragraph.
+fn paragraphs(lines: &[Line],gap: f64)-> Vec<(Timestamp,String)>{
+    let mut result: Vec<(Timestamp,String)> = Vec::new();
+    let mut last_end = Timestamp::ZERO;
+    for line in lines.iter(){
+        let text = line.text();
+        let content = text.split('\n').map(|x| x.trim()).filter(|x| !x.is_empty()).collect::<Vec<&str>>();
+        if content.is_empty(){
+            continue;
+        }
+        if result.is_empty() || (line.start - last_end).as_secs_f64() > gap{
+            result.push((line.start,String::new()));
+        }
+        let text = &mut result.last_mut().unwrap().1;
+        for part in content{
+            join_text(text,part);
+        }
+        last_end = line.end;
+    }
+    result
+}
+
+fn format_transcript_time(time: Timestamp)-> String{
+    let (hour,minute,second,_) = time.clock(0);
+    format!("{:0>2}:{:0>2}:{:0>2}",hour,minute,second)
+}
+
+/// plain text transcript.
+pub struct Txt{
+    gap: f64,
+    timestamps: bool,
+}
+
+impl Txt{
+    /// `gap` is the silence in seconds that starts a new paragraph.
+    pub fn new(gap: f64,timestamps: bool)->Self{
+        Txt{gap,timestamps}
+    }
+}
+
+impl Formatter for Txt{
+    fn ext(&self)->&str{
+        "txt"
+    }
+
+    fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        for (start,text) in paragraphs(&subtitle.lines,self.gap){
+            if self.timestamps{
+                write!(writer,"[{}] ",format_transcript_time(start))?;
+            }
+            write!(writer,"{}\n\n",text)?;
+        }
+        Ok(())
+    }
+
+}
+
+/// markdown transcript. timestamps link back to the video when it is known.
+pub struct Md{
+    gap: f64,
+    timestamps: bool,
+}
+
+impl Md{
+    /// `gap` is the silence in seconds that starts a new paragraph.
+    pub fn new(gap: f64,timestamps: bool)->Self{
+        Md{gap,timestamps}
+    }
+
+    fn escape(text: &str)-> String{
+        let mut result = String::with_capacity(text.len());
+        for c in text.chars(){
+            if matches!(c,'\\'|'`'|'*'|'_'|'['|']'|'<'|'>'|'#'){
+                result.push('\\');
+            }
+            result.push(c);
+        }
+        result
+    }
+}
+
+impl Formatter for Md{
+    fn ext(&self)->&str{
+        "md"
+    }
+
+    fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        write!(writer,"# {}\n\n",Self::escape(&subtitle.name))?;
+        for (start,text) in paragraphs(&subtitle.lines,self.gap){
+            if self.timestamps{
+                let time = format_transcript_time(start);
+                match &subtitle.meta{
+                    // the page of the video, or the bangumi episode it came from.
+                    Some(meta) => {
+                        let url = meta.url();
+                        let separator = if url.contains('?') { '&' } else { '?' };
+                        write!(writer,"[{}]({}{}t={}) ",time,url,separator,start.as_millis().max(0)/1000)?
+                    },
+                    None => write!(writer,"\\[{}\\] ",time)?,
+                }
+            }
+            write!(writer,"{}\n\n",Self::escape(&text))?;
+        }
+        Ok(())
+    }
+
+}
+
+/// bilibili cc format. the file accepted by the bilibili subtitle editor.
+#[derive(Default)]
+pub struct Bcc{
+}
+
+#[derive(Serialize)]
+struct BccFile<'a>{
+    font_size: f64,
+    font_color: &'a str,
+    background_alpha: f64,
+    background_color: &'a str,
+    #[serde(rename = "Stroke")]
+    stroke: &'a str,
+    body: Vec<BccLine>,
+}
+
+#[derive(Serialize)]
+struct BccLine{
+    from: f64,
+    to: f64,
+    location: u8,
+    content: String,
+}
+
+impl Bcc{
+    pub fn new()->Self{
+        Bcc{}
+    }
+}
+
+impl Formatter for Bcc{
+    fn ext(&self)->&str{
+        "bcc"
+    }
+
+    fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        let default_style = BccStyle::default();
+        let style = subtitle.style.as_ref().unwrap_or(&default_style);
+        let file = BccFile{
+            font_size: style.font_size,
+            font_color: &style.font_color,
+            background_alpha: style.background_alpha,
+            background_color: &style.background_color,
+            stroke: &style.stroke,
+            body: subtitle.lines.iter()
+                .map(|line| BccLine{
+                    from: line.start.as_secs_f64(),
+                    to: line.end.as_secs_f64(),
+                    location: line.location.unwrap_or(2),
+                    content: line.text(),
+                })
+                .collect(),
+        };
+        serde_json::to_writer(writer,&file)?;
+        Ok(())
+    }
+
+}
+
+/// the json interchange format of bccdc, a `CcSubtitle` as it is.
+///
+/// ```json
+/// {
+///   "format": "bccdc",
+///   "version": 1,
+///   "name": "BV1kv411P7Ek-zh-Hans",
+///   "lan": "zh-Hans",
+///   "lan_doc": "中文（简体）",
+///   "style": {"font_size": 0.4,"font_color": "#FFFFFF","background_alpha": 0.5,"background_color": "#9C27B0","stroke": "none"},
+///   "meta": {"bvid": "BV1kv411P7Ek","cid": 1,"page": 1,"duration": 60000,"title": null,"part": null,"source": null},
+///   "lines": [{"content": "text","start": 1000,"end": 2500,"location": 2,"secondary": null}]
+/// }
+/// ```
+///
+/// times are whole milliseconds. `lan`, `lan_doc`, `style`, `meta` and the
+/// `location` and `secondary` of a line may be null or left out.
+/// `version` is raised whenever a change would break an existing reader.
+#[derive(Default)]
+pub struct Json{
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a>{
+    format: &'a str,
+    version: u32,
+    #[serde(flatten)]
+    subtitle: &'a CcSubtitle,
+}
+
+#[derive(Deserialize)]
+struct JsonFileOwned{
+    format: String,
+    version: u32,
+    #[serde(flatten)]
+    subtitle: CcSubtitle,
+}
+
+impl Json{
+    /// the `format` field every file carries.
+    pub const FORMAT: &'static str = "bccdc";
+    /// the schema version written by this build. older versions are read as well.
+    pub const VERSION: u32 = 1;
+
+    pub fn new()->Self{
+        Json{}
+    }
+
+    /// whether `content` looks like the interchange format rather than another json, e.g. bcc.
+    pub fn detect(content: &str)-> bool{
+        serde_json::from_str::<serde_json::Value>(content)
+            .is_ok_and(|value| value["format"] == Self::FORMAT)
+    }
+
+    pub fn parse(name: &str, content: &str)-> Result<CcSubtitle,Error>{
+        let file: JsonFileOwned = serde_json::from_str(content.trim_start_matches('\u{feff}'))?;
+        if file.format != Self::FORMAT{
+            return Err(invalid_data(format!("not a {} json file",Self::FORMAT)));
+        }
+        if file.version == 0 || file.version > Self::VERSION{
+            return Err(invalid_data(format!("unsupported json version {}",file.version)));
+        }
+        let mut subtitle = file.subtitle;
+        subtitle.name = name.to_string();
+        Ok(subtitle)
+    }
+}
+
+impl Formatter for Json{
+    fn ext(&self)->&str{
+        "json"
+    }
+
+    fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+        let file = JsonFile{format: Self::FORMAT,version: Self::VERSION,subtitle};
+        serde_json::to_writer_pretty(&mut *writer,&file)?;
+        writeln!(writer)
+    }
+
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::cc::{CcSubtitle,Line,Formatter,Srt,Ass,Vtt,Ttml,TtmlTime,Sbv,Srv3,Lrc,Txt,Md,Json,VideoMeta,Sub,BccStyle,Anchor,NormalizeOptions,ReflowOptions,CheckOptions,Severity,Timestamp};
+    use crate::lookup;
+    use crate::zh::ZhVariant;
+
+    fn secs(seconds: f64)-> Timestamp{
+        Timestamp::from_secs_f64(seconds)
+    }
+
+    #[test]
+    fn parse_srt_test(){
+        let content = "\u{feff}1\r\n00:00:01,500 --> 00:00:03.250\r\n第一行\r\nsecond line\r\n\r\n00:01:02,000 --> 01:00:00,001 X1:10 X2:20\r\nno index\r\n";
+        let subtitle = Srt::parse("test",content).unwrap();
+        assert_eq!(subtitle.name,"test");
+        assert_eq!(subtitle.lines.len(),2);
+        assert_eq!(subtitle.lines[0].start,secs(1.5));
+        assert_eq!(subtitle.lines[0].end,secs(3.25));
+        assert_eq!(subtitle.lines[0].content,"第一行\nsecond line");
+        assert_eq!(subtitle.lines[1].start,secs(62.));
+        assert_eq!(subtitle.lines[1].end,secs(3600.001));
+        assert_eq!(subtitle.lines[1].content,"no index");
+    }
+
+    #[test]
+    fn parse_invalid_srt_test(){
+        assert!(Srt::parse("test","hello\nworld").is_err());
+    }
+
+    #[test]
+    fn parse_ass_test(){
+        let content = "[Script Info]\nScriptType: v4.00+\n\n[Events]\n\
+            Format: Layer, Start, End, Style, Actor, MarginL, MarginR, MarginV, Effect, Text\n\
+            Comment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,ignored\n\
+            Dialogue: 0,0:00:05.10,0:00:06.00,Default,,0,0,0,,{\\an8}second, with comma\n\
+            Dialogue: 0,0:00:01.50,0:00:03.25,Default,,0,0,0,,{\\i1}first{\\i0}\\Nline\n";
+        let subtitle = Ass::parse("test",content).unwrap();
+        assert_eq!(subtitle.lines.len(),2);
+        assert_eq!(subtitle.lines[0].start,secs(1.5));
+        assert_eq!(subtitle.lines[0].end,secs(3.25));
+        assert_eq!(subtitle.lines[0].content,"first\nline");
+        assert_eq!(subtitle.lines[1].content,"second, with comma");
+    }
+
+    #[test]
+    fn parse_vtt_test(){
+        let content = "WEBVTT - test\r\n\r\nSTYLE\r\n::cue { color: white }\r\n\r\n\
+            NOTE a comment\r\n\r\n\
+            intro\r\n00:01.500 --> 00:03.250 line:10% align:start\r\nTom &amp; Jerry &lt;3\r\nsecond\r\n\r\n\
+            01:00:00.000 --> 01:00:01.000\r\nlast\r\n";
+        let subtitle = Vtt::parse("test",content).unwrap();
+        assert_eq!(subtitle.lines.len(),2);
+        assert_eq!(subtitle.lines[0].start,secs(1.5));
+        assert_eq!(subtitle.lines[0].end,secs(3.25));
+        assert_eq!(subtitle.lines[0].content,"Tom & Jerry <3\nsecond");
+        assert_eq!(subtitle.lines[0].location,Some(7));
+        assert_eq!(subtitle.lines[1].start,secs(3600.));
+        assert_eq!(subtitle.lines[1].content,"last");
+        assert_eq!(subtitle.lines[1].location,None);
+    }
+
+    #[test]
+    fn parse_vtt_markup_test(){
+        let content = "WEBVTT\n\n00:01.000 --> 00:02.000\n<v Bob>hi <b>there</b> <c.x>&lt;3</c> <00:00:01.500>later\n";
+        let subtitle = Vtt::parse("test",content).unwrap();
+        assert_eq!(subtitle.lines[0].content,"hi there <3 later");
+
+        let mut source = CcSubtitle::new("test",vec![]);
+        for location in 1..=9{
+            let mut line = Line::new(secs(1.),secs(2.),location.to_string());
+            line.location = Some(location);
+            source.lines.push(line);
+        }
+        let mut buffer = Vec::new();
+        Vtt::new().write(&mut buffer,&source).unwrap();
+        let parsed = Vtt::parse("test",&String::from_utf8(buffer).unwrap()).unwrap();
+        let locations: Vec<u8> = parsed.lines.iter().map(|x| x.position()).collect();
+        assert_eq!(locations,(1..=9).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn write_ttml_test(){
+        let mut subtitle = CcSubtitle::new("a <b>",vec![Line::new(secs(1.5),secs(3.25),String::from("Tom & Jerry\nsecond"))]);
+        subtitle.lan = Some(String::from("ai-zh"));
+
+        let mut output = Vec::new();
+        Ttml::new(TtmlTime::Clock).write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("xml:lang=\"zh\""));
+        assert!(output.contains("<ttm:title>a &lt;b&gt;</ttm:title>"));
+        assert!(output.contains("<p begin=\"00:00:01.500\" end=\"00:00:03.250\">Tom &amp; Jerry<br/>second</p>"));
+
+        let mut output = Vec::new();
+        Ttml::new(TtmlTime::Ticks(10_000_000)).write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("ttp:tickRate=\"10000000\""));
+        assert!(output.contains("<p begin=\"15000000t\" end=\"32500000t\">"));
+    }
+
+    #[test]
+    fn write_youtube_test(){
+        let subtitle = CcSubtitle::new("test",vec![Line::new(secs(3661.5),secs(3663.25),String::from("<3\nsecond"))]);
+
+        let mut output = Vec::new();
+        Sbv::new().write(&mut output,&subtitle).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),"1:01:01.500,1:01:03.250\n<3\nsecond\n\n");
+
+        let mut output = Vec::new();
+        Srv3::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("<p t=\"3661500\" d=\"1750\">&lt;3\nsecond</p>"));
+    }
+
+    #[test]
+    fn write_lrc_test(){
+        let subtitle = CcSubtitle::new("artist - title",vec![Line::new(secs(61.504),secs(63.255),String::from("first\nsecond"))]);
+
+        let mut output = Vec::new();
+        Lrc::new(false).write(&mut output,&subtitle).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),"[ti:title]\n[ar:artist]\n[re:bccdc]\n[01:01.50]first second\n");
+
+        let mut output = Vec::new();
+        Lrc::new(true).write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().ends_with("[01:01.50]first second <01:03.26>\n"));
+    }
+
+    #[test]
+    fn write_transcript_test(){
+        let mut subtitle = CcSubtitle::new("test",vec![
+            Line::new(secs(1.),secs(2.),String::from("hello")),
+            Line::new(secs(2.5),secs(3.),String::from("world")),
+            Line::new(secs(3.),secs(4.),String::from("你好")),
+            Line::new(secs(62.),secs(63.),String::from("*next*")),
+        ]);
+
+        let mut output = Vec::new();
+        Txt::new(2.,false).write(&mut output,&subtitle).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),"hello world你好\n\n*next*\n\n");
+
+        let mut output = Vec::new();
+        Txt::new(2.,true).write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().ends_with("[00:01:02] *next*\n\n"));
+
+        subtitle.meta = Some(VideoMeta::new("BV1mT42127CQ",1514080023,2));
+        let mut output = Vec::new();
+        Md::new(2.,true).write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().ends_with("[00:01:02](https://www.bilibili.com/video/BV1mT42127CQ?p=2&t=62) \\*next\\*\n\n"));
+
+        subtitle.meta.as_mut().unwrap().source = Some(String::from("https://www.bilibili.com/bangumi/play/ep475902"));
+        let mut output = Vec::new();
+        Md::new(2.,true).write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with("[00:01:02](https://www.bilibili.com/bangumi/play/ep475902?t=62) \\*next\\*\n\n"));
+        assert!(!output.contains("/video/"));
+    }
+
+    #[test]
+    fn write_sub_test(){
+        let subtitle = CcSubtitle::new("test",vec![Line::new(secs(1.),secs(2.),String::from("first\nsecond"))]);
+        let mut output = Vec::new();
+        Sub::new(25.).write(&mut output,&subtitle).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),"{1}{1}25\n{25}{50}first|second\n");
+    }
+
+    #[test]
+    fn write_location_test(){
+        let json= "{\"body\":[{\"content\":\"top\",\"from\":1.0,\"location\":8,\"to\":2.0},{\"content\":\"bottom\",\"from\":3.0,\"location\":2,\"to\":4.0}]}";
+        let subtitle = lookup::json_to_subtitle("test",json).unwrap();
+
+        let mut output = Vec::new();
+        Ass::new().write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(",,{\\an8}top\n"));
+        assert!(output.contains(",,bottom\n"));
+        assert_eq!(Ass::parse("test",&output).unwrap().lines[0].location,Some(8));
+
+        let mut output = Vec::new();
+        Vtt::new().write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("00:00:01.000 --> 00:00:02.000 line:10%\n"));
+        assert!(output.contains("00:00:03.000 --> 00:00:04.000\n"));
+    }
+
+    #[test]
+    fn write_style_test(){
+        let mut subtitle = CcSubtitle::new("test",vec![Line::new(secs(1.),secs(2.),String::from("hello"))]);
+        subtitle.style = Some(BccStyle{
+            font_size: 0.6,
+            font_color: String::from("#FF8000"),
+            background_alpha: 0.5,
+            background_color: String::from("#9C27B0"),
+            stroke: String::from("none"),
+        });
+
+        let mut output = Vec::new();
+        Ass::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("Style: Default,Arial,30,&H000080FF,&H0000FFFF,&H80B0279C,&H80B0279C,0,0,0,0,100,100,0,0,3,1,0,2,10,10,10,1\n"));
+
+        let mut output = Vec::new();
+        Vtt::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().starts_with("WEBVTT\n\nSTYLE\n::cue {\n  color: #FF8000;\n  background-color: rgba(156,39,176,0.5);\n  font-size: 150%;\n}\n\n"));
+
+        let mut output = Vec::new();
+        Srt::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("<font color=\"#FF8000\">hello</font>\n"));
+
+        let style = subtitle.style.as_mut().unwrap();
+        style.background_alpha = 0.;
+        style.stroke = String::from("#00FF00");
+        let mut output = Vec::new();
+        Ass::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("&H000080FF,&H0000FFFF,&H0000FF00,&H0000FF00,0,0,0,0,100,100,0,0,1,"));
+    }
+
+    #[test]
+    fn write_ass_template_test(){
+        let subtitle = CcSubtitle::new("test",vec![Line::new(secs(1.),secs(2.),String::from("hello"))]);
+        let template = "[Script Info]\nTitle: house\nPlayResX: 640\nPlayResY: 480\n\n[V4+ Styles]\nStyle: Default,Noto Sans,60\n\n[Events]\nDialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,old\n";
+
+        let mut output = Vec::new();
+        Ass::new().template(template.to_string()).play_res(1920,1080).write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("[Script Info]\nPlayResX: 1920\nPlayResY: 1080\nTitle: house\n\n[V4+ Styles]\nStyle: Default,Noto Sans,60\n\n[Events]\n"));
+        assert!(!output.contains("old"));
+        assert!(output.contains(",,hello\n"));
+
+        let mut output = Vec::new();
+        Ass::new().play_res(1920,1080).write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("PlayResX: 1920\nPlayResY: 1080\n"));
+        assert!(output.contains("Style: Default,Arial,75,"));
+
+        let mut subtitle = subtitle;
+        subtitle.lines[0].secondary = Some(String::from("world"));
+        let template = "[Script Info]\n\n[V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour\nStyle: Default,Noto Sans,60,&H00FFFFFF\n\n[Events]\n";
+        let mut output = Vec::new();
+        Ass::new().template(template.to_string()).write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Style: Default,Noto Sans,60,&H00FFFFFF\nStyle: Secondary,Noto Sans,45,&H00FFFFFF\n\n[Events]\n"));
+        assert!(output.contains(",,hello\\N{\\rSecondary}world\n"));
+
+        // a template defining Secondary itself is kept as it is, one without styles gets a section.
+        let template = "[V4+ Styles]\nStyle: Secondary,Noto Sans,10\nStyle: Default,Noto Sans,60\n";
+        let mut output = Vec::new();
+        Ass::new().template(template.to_string()).write(&mut output,&subtitle).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap().matches("Style: Secondary").count(),1);
+        let mut output = Vec::new();
+        Ass::new().template("[Script Info]\nTitle: x\n".to_string()).write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("Title: x\n\n[V4+ Styles]\nFormat: Name, Fontname, Fontsize,"));
+
+        // a template without WrapStyle still breaks the lines of a cue.
+        let subtitle = CcSubtitle::new("test",vec![Line::new(secs(1.),secs(2.),String::from("line one\nline two"))]);
+        let mut output = Vec::new();
+        Ass::new().template("[Script Info]\nTitle: x\n".to_string()).write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains(",,line one\\Nline two\n"));
+    }
+
+    #[test]
+    fn bilingual_test(){
+        let mut primary = CcSubtitle::new("zh",vec![
+            Line::new(secs(1.),secs(3.),String::from("你好")),
+            Line::new(secs(3.),secs(5.),String::from("世界")),
+        ]);
+        primary.lan = Some(String::from("zh-Hans"));
+        let mut secondary = CcSubtitle::new("en",vec![
+            Line::new(secs(0.9),secs(3.2),String::from("hello")),
+            Line::new(secs(3.1),secs(5.),String::from("world")),
+            Line::new(secs(6.),secs(7.),String::from("alone")),
+        ]);
+        secondary.lan = Some(String::from("en"));
+
+        let subtitle = primary.bilingual(&secondary);
+        assert_eq!(subtitle.lan.as_deref(),Some("zh-Hans"));
+        assert_eq!(subtitle.lines.len(),3);
+        assert_eq!(subtitle.lines[0].text(),"你好\nhello");
+        assert_eq!(subtitle.lines[1].text(),"世界\nworld");
+        assert_eq!(subtitle.lines[2].text(),"alone");
+
+        let mut output = Vec::new();
+        Ass::new().write(&mut output,&subtitle).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Style: Secondary,Arial,15,&H00FFFFFF,"));
+        assert!(output.contains(",,你好\\N{\\rSecondary}hello\n"));
+        assert!(output.contains(",,{\\rSecondary}alone\n"));
+    }
+
+    #[test]
+    fn transform_test(){
+        let mut subtitle = CcSubtitle::new("test",vec![
+            Line::new(secs(0.2),secs(0.4),String::from("dropped")),
+            Line::new(secs(1.),secs(2.),String::from("kept")),
+        ]);
+        subtitle.shift(-500);
+        assert_eq!(subtitle.lines.len(),1);
+        assert_eq!(subtitle.lines[0].start,secs(0.5));
+        assert_eq!(subtitle.lines[0].end,secs(1.5));
+
+        subtitle.scale(2.);
+        assert_eq!(subtitle.lines[0].start,secs(1.));
+        assert_eq!(subtitle.lines[0].end,secs(3.));
+
+        subtitle.convert_fps(25.,50.);
+        assert_eq!(subtitle.lines[0].start,secs(0.5));
+        assert_eq!(subtitle.lines[0].end,secs(1.5));
+    }
+
+    #[test]
+    fn resync_test(){
+        let mut subtitle = CcSubtitle::new("test",vec![
+            Line::new(secs(10.),secs(11.),String::from("first")),
+            Line::new(secs(20.),secs(22.),String::from("second")),
+            Line::new(secs(30.),secs(31.),String::from("third")),
+        ]);
+        subtitle.resync(("@1".parse().unwrap(),secs(15.)),("00:30".parse().unwrap(),secs(35.))).unwrap();
+        assert_eq!(subtitle.lines[0].start,secs(15.));
+        assert_eq!(subtitle.lines[1].start,secs(25.));
+        assert_eq!(subtitle.lines[1].end,secs(27.));
+        assert_eq!(subtitle.lines[2].start,secs(35.));
+
+        assert!(subtitle.resync((Anchor::Cue(4),secs(1.)),(Anchor::Time(secs(1.)),secs(2.))).is_err());
+        assert!(subtitle.resync((Anchor::Cue(1),secs(1.)),(Anchor::Time(secs(15.)),secs(2.))).is_err());
+        assert!(subtitle.resync((Anchor::Cue(1),secs(30.)),(Anchor::Cue(3),secs(10.))).is_err());
+        assert!(subtitle.resync((Anchor::Cue(1),secs(10.)),(Anchor::Cue(3),secs(10.))).is_err());
+        assert_eq!(subtitle.lines[0].start,secs(15.));
+    }
+
+    #[test]
+    fn normalize_test(){
+        let lines = || vec![
+            Line::new(secs(5.),secs(6.),String::from("overlap")),
+            Line::new(secs(0.),secs(1.),String::from("repeat")),
+            Line::new(secs(1.),secs(2.),String::from("repeat")),
+            Line::new(secs(3.),secs(3.),String::from("zero")),
+            Line::new(secs(4.),secs(4.),String::from(" ")),
+            Line::new(secs(5.5),secs(7.),String::from("later")),
+        ];
+        let mut subtitle = CcSubtitle::new("test",lines());
+        subtitle.normalize(&NormalizeOptions::default());
+        let result: Vec<(f64,f64,&str)> = subtitle.lines.iter().map(|x| (x.start.as_secs_f64(),x.end.as_secs_f64(),x.content.as_str())).collect();
+        assert_eq!(result,vec![(0.,2.,"repeat"),(3.,3.7,"zero"),(5.,7.,"overlap\nlater")]);
+
+        let mut subtitle = CcSubtitle::new("test",lines());
+        subtitle.normalize(&NormalizeOptions{merge_overlaps: true,..Default::default()});
+        assert_eq!(subtitle.lines[2].content,"overlap\nlater");
+        assert_eq!(subtitle.lines[2].end,secs(7.));
+
+        // cues starting together, closer than the minimum gap, or short but fitting before the next one.
+        let mut subtitle = CcSubtitle::new("test",vec![
+            Line::new(secs(0.),secs(2.),String::from("a")),
+            Line::new(secs(0.),secs(1.),String::from("b")),
+            Line::new(secs(3.),secs(3.05),String::from("c")),
+            Line::new(secs(3.05),secs(5.),String::from("d")),
+            Line::new(secs(6.),secs(6.2),String::from("e")),
+            Line::new(secs(6.5),secs(8.),String::from("f")),
+        ]);
+        subtitle.normalize(&NormalizeOptions::default());
+        let result: Vec<(f64,f64,&str)> = subtitle.lines.iter().map(|x| (x.start.as_secs_f64(),x.end.as_secs_f64(),x.content.as_str())).collect();
+        assert_eq!(result,vec![(0.,2.,"a\nb"),(3.,5.,"c\nd"),(6.,6.42,"e"),(6.5,8.,"f")]);
+        assert!(subtitle.lines.iter().all(|x| x.end > x.start));
+    }
+
+    #[test]
+    fn reflow_test(){
+        let lines = || vec![
+            Line::new(secs(0.),secs(34.),String::from("今天天气很好，我们一起去公园散步吧")),
+            Line::new(secs(34.),secs(40.),String::from("the quick brown\nfox jumps")),
+        ];
+        let mut subtitle = CcSubtitle::new("test",lines());
+        subtitle.reflow(&ReflowOptions{max_width: 20,max_lines: 2});
+        assert_eq!(subtitle.lines[0].content,"今天天气很好，\n我们一起去公园散步吧");
+        assert_eq!(subtitle.lines[1].content,"the quick brown fox\njumps");
+
+        let mut subtitle = CcSubtitle::new("test",vec![Line::new(secs(0.),secs(60.),String::from("the total was 1,000,000 dollars, e.g. 3.14 each"))]);
+        subtitle.reflow(&ReflowOptions{max_width: 20,max_lines: 3});
+        assert_eq!(subtitle.lines[0].content,"the total was\n1,000,000 dollars,\ne.g. 3.14 each");
+
+        let mut subtitle = CcSubtitle::new("test",lines());
+        subtitle.reflow(&ReflowOptions{max_width: 20,max_lines: 1});
+        let result: Vec<(f64,f64,&str)> = subtitle.lines.iter().map(|x| (x.start.as_secs_f64(),x.end.as_secs_f64(),x.content.as_str())).collect();
+        assert_eq!(result[..2],vec![(0.,14.,"今天天气很好，"),(14.,34.,"我们一起去公园散步吧")]);
+        assert_eq!(subtitle.lines.len(),4);
+    }
+
+    #[test]
+    fn convert_zh_test(){
+        let mut sub = CcSubtitle::new("zh-Hant",vec![Line::new(secs(0.),secs(1.),"這裡是軟體".to_string())]);
+        sub.lan = Some("zh-Hant".to_string());
+        sub.lan_doc = Some("中文（繁體）+English".to_string());
+        sub.lines[0].secondary = Some("here".to_string());
+        sub.convert_zh(ZhVariant::Hans);
+        assert_eq!(sub.lines[0].content,"这里是软体");
+        assert_eq!(sub.lines[0].secondary.as_deref(),Some("here"));
+        assert_eq!(sub.lan.as_deref(),Some("zh-Hans"));
+        assert_eq!(sub.lan_doc.as_deref(),Some("中文（简体）+English"));
+    }
+
+    #[test]
+    fn check_test(){
+        let mut lines = vec![
+            Line::new(secs(1.),secs(3.),String::from("hello")),
+            Line::new(secs(2.5),secs(2.),String::from("world")),
+            Line::new(secs(4.),secs(5.),String::from(" ")),
+            Line::new(secs(6.),secs(6.5),String::from("a very long line to read")),
+            Line::new(secs(-1.),secs(1.),String::from("before")),
+            Line::new(secs(9.),secs(12.),String::from("late")),
+            Line::new(secs(13.),secs(14.),String::from("after")),
+            Line::new(secs(2.),secs(3.),String::from("top")),
+        ];
+        lines[7].location = Some(8);
+        let mut subtitle = CcSubtitle::new("test",lines);
+        subtitle.meta = Some(VideoMeta{duration: Some(secs(10.)),..VideoMeta::new("BV1mT42127CQ",1514080023,1)});
+        let issues: Vec<(usize,Severity)> = subtitle.check(&CheckOptions::default()).iter().map(|x| (x.cue,x.severity)).collect();
+        assert_eq!(issues,vec![
+            (2,Severity::Error),
+            (2,Severity::Warning),
+            (3,Severity::Warning),
+            (4,Severity::Warning),
+            (5,Severity::Error),
+            (6,Severity::Warning),
+            (7,Severity::Error),
+        ]);
+    }
+
+    #[test]
+    fn timestamp_test(){
+        assert_eq!(Timestamp::parse("00:00:01,9999"),Some(Timestamp::from_millis(2000)));
+        assert_eq!(Timestamp::parse("01:02.5"),Some(Timestamp::from_millis(62_500)));
+        assert_eq!(Timestamp::parse("1.25"),Some(Timestamp::from_millis(1250)));
+        assert_eq!(Timestamp::parse("1:x"),None);
+        assert_eq!(secs(1.9999),Timestamp::from_millis(2000));
+        assert_eq!(Timestamp::from_millis(1995).clock(2),(0,0,2,0));
+        assert_eq!(Timestamp::from_millis(-500).clock(3),(0,0,0,0));
+        assert_eq!(Timestamp::from_millis(-1500).to_string(),"-00:00:01.500");
+        assert_eq!(Timestamp::from_millis(1000) * 1.0005,Timestamp::from_millis(1001));
+
+        let subtitle = CcSubtitle::new("test",vec![Line::new(secs(1.9999),secs(3599.996),String::from("hello"))]);
+        let mut output = Vec::new();
+        Srt::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("00:00:02,000 --> 00:59:59,996\n"));
+        let mut output = Vec::new();
+        Ass::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("Dialogue: 0,0:00:02.00,1:00:00.00,"));
+    }
+
+    #[test]
+    fn write_meta_test(){
+        let mut subtitle = CcSubtitle::new("test",vec![Line::new(secs(1.),secs(2.),String::from("hello"))]);
+        subtitle.meta = Some(VideoMeta{
+            title: Some(String::from("title")),
+            part: Some(String::from("part\none")),
+            ..VideoMeta::new("BV1mT42127CQ",1514080023,2)
+        });
+
+        let mut output = Vec::new();
+        Ass::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("Title: test\n; Video: title\n; Part: part one\n; Source: https://www.bilibili.com/video/BV1mT42127CQ?p=2\n; BVID: BV1mT42127CQ\n; CID: 1514080023\n; Page: 2\n"));
+
+        let mut output = Vec::new();
+        Ass::new().template(String::from("[Script Info]\nTitle: house\n")).write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().starts_with("[Script Info]\n; Video: title\n"));
+
+        let mut output = Vec::new();
+        Vtt::new().write(&mut output,&subtitle).unwrap();
+        assert!(String::from_utf8(output).unwrap().starts_with("WEBVTT\n\nNOTE\nVideo: title\nPart: part one\n"));
+
+        let mut output = Vec::new();
+        subtitle.meta.as_ref().unwrap().write_json(&mut output).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(json["url"],"https://www.bilibili.com/video/BV1mT42127CQ?p=2");
+        assert_eq!(json["page"],2);
+        assert!(json["source"].is_null());
+        let mut parsed: VideoMeta = serde_json::from_value(json).unwrap();
+        assert_eq!(Some(&parsed),subtitle.meta.as_ref());
+        parsed.duration = Some(secs(1.5));
+        let mut output = Vec::new();
+        parsed.write_json(&mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("\"duration\": 1500,"));
+    }
+
+    #[test]
+    fn json_test(){
+        let mut subtitle = CcSubtitle::new("test",vec![Line::new(secs(1.0),secs(2.5),String::from("第一行\n\"quoted\""))]);
+        subtitle.lines[0].location = Some(8);
+        subtitle.lines.push(Line::new(secs(3.0),secs(4.0),String::from("second")));
+        subtitle.lines[1].secondary = Some(String::from("第二行"));
+        subtitle.lan = Some(String::from("zh-Hans"));
+        subtitle.style = Some(BccStyle::default());
+        let mut meta = VideoMeta::new("BV1xx411c7mD",42,2);
+        meta.duration = Some(secs(60.0));
+        subtitle.meta = Some(meta);
+
+        let mut buffer = Vec::new();
+        Json::new().write(&mut buffer,&subtitle).unwrap();
+        let content = String::from_utf8(buffer).unwrap();
+        assert!(content.contains("\"format\": \"bccdc\""));
+        assert!(content.contains("\"version\": 1"));
+        assert!(content.contains("\"start\": 1000"));
+        assert!(content.contains("\"page\": 2"));
+        assert!(Json::detect(&content));
+
+        let parsed = Json::parse("other",&content).unwrap();
+        assert_eq!(parsed.name,"other");
+        assert_eq!(parsed.lan.as_deref(),Some("zh-Hans"));
+        assert_eq!(parsed.style,subtitle.style);
+        assert_eq!(parsed.meta,subtitle.meta);
+        assert_eq!(parsed.lines.len(),2);
+        assert_eq!(parsed.lines[0].content,"第一行\n\"quoted\"");
+        assert_eq!((parsed.lines[0].start,parsed.lines[0].end,parsed.lines[0].location),(secs(1.0),secs(2.5),Some(8)));
+        assert_eq!(parsed.lines[1].secondary.as_deref(),Some("第二行"));
+
+        let minimal = r#"{"format":"bccdc","version":1,"name":"x","lines":[{"content":"a","start":0,"end":500}]}"#;
+        let parsed = Json::parse("min",minimal).unwrap();
+        assert!(parsed.lan.is_none() && parsed.meta.is_none());
+        assert_eq!(parsed.lines[0].end,Timestamp::from_millis(500));
+        assert!(Json::parse("x",r#"{"format":"bccdc","version":2,"name":"x","lines":[]}"#).is_err());
+        assert!(!Json::detect(r#"{"body":[]}"#));
+
+        let loaded = lookup::content_to_subtitle("file",&content).unwrap();
+        assert_eq!(loaded.lines.len(),2);
+    }
+
+    #[test]
+    fn parse_srt_markup_test(){
+        let content = "1\n00:00:01,000 --> 00:00:02,000\n<i>hi</i> <FONT color=\"#FF0000\">red</FONT>\n\nworld after blank\n\n2\n00:00:03,000 --> 00:00:04,000\n<b>a < b</b>\n";
+        let subtitle = Srt::parse("test",content).unwrap();
+        assert_eq!(subtitle.lines.len(),2);
+        assert_eq!(subtitle.lines[0].content,"hi red\nworld after blank");
+        assert_eq!(subtitle.lines[1].content,"a < b");
+        let mut buffer = Vec::new();
+        Ass::new().write(&mut buffer,&subtitle).unwrap();
+        assert!(String::from_utf8(buffer).unwrap().contains(",hi red\\Nworld after blank"));
+    }
+}
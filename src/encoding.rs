@@ -0,0 +1,199 @@
+use std::io::{Error,ErrorKind,Write};
+use std::str::FromStr;
+
+/// text encoding of written subtitle files.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Encoding{
+    Utf8,
+    /// utf-8 starting with a byte order mark.
+    Utf8Bom,
+    /// utf-16 little endian starting with a byte order mark.
+    Utf16Le,
+    Gbk,
+}
+
+impl Encoding{
+    fn bom(&self)-> &'static [u8]{
+        match self{
+            Encoding::Utf8Bom => b"\xEF\xBB\xBF",
+            Encoding::Utf16Le => b"\xFF\xFE",
+            _ => b"",
+        }
+    }
+
+    fn name(&self)-> &'static str{
+        match self{
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Gbk => "gbk",
+        }
+    }
+
+    fn encode(&self,text: &str)-> Option<Vec<u8>>{
+        match self{
+            Encoding::Utf8|Encoding::Utf8Bom => Some(text.as_bytes().to_vec()),
+            Encoding::Utf16Le => Some(text.encode_utf16().flat_map(|x| x.to_le_bytes()).collect()),
+            Encoding::Gbk => {
+                let (bytes,_,unmappable) = encoding_rs::GBK.encode(text);
+                if unmappable { None } else { Some(bytes.into_owned()) }
+            },
+        }
+    }
+}
+
+impl FromStr for Encoding{
+    type Err = String;
+
+    fn from_str(s: &str)-> Result<Self,Self::Err>{
+        match s.to_lowercase().as_str(){
+            "utf8"|"utf-8" => Ok(Encoding::Utf8),
+            "utf8-bom"|"utf-8-bom" => Ok(Encoding::Utf8Bom),
+            "utf16le"|"utf-16le" => Ok(Encoding::Utf16Le),
+            "gbk" => Ok(Encoding::Gbk),
+            _ => Err(format!("unknown encoding {}",s)),
+        }
+    }
+}
+
+/// line ending of written subtitle files.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Eol{
+    Lf,
+    Crlf,
+}
+
+impl FromStr for Eol{
+    type Err = String;
+
+    fn from_str(s: &str)-> Result<Self,Self::Err>{
+        match s.to_lowercase().as_str(){
+            "lf" => Ok(Eol::Lf),
+            "crlf" => Ok(Eol::Crlf),
+            _ => Err(format!("unknown line ending {}",s)),
+        }
+    }
+}
+
+/// sits between a `Formatter` and the file. formatters write utf-8 with `\n`,
+/// which is converted to `eol` and encoded to `encoding` on the way through.
+/// call `finish` once the formatter is done.
+pub struct EncodedWriter<'a>{
+    inner: &'a mut dyn Write,
+    encoding: Encoding,
+    eol: Eol,
+    /// the tail of a char split between two writes.
+    pending: Vec<u8>,
+    started: bool,
+    last_cr: bool,
+    /// 1-based line number of the output, used in errors.
+    line: usize,
+}
+
+impl<'a> EncodedWriter<'a>{
+    pub fn new(inner: &'a mut dyn Write,encoding: Encoding,eol: Eol)->Self{
+        EncodedWriter{inner,encoding,eol,pending: Vec::new(),started: false,last_cr: false,line: 1}
+    }
+
+    /// write the byte order mark of an empty output and report a char left incomplete.
+    pub fn finish(mut self)-> Result<(),Error>{
+        if !self.pending.is_empty(){
+            return Err(Error::new(ErrorKind::InvalidData,"output ends inside a utf-8 char"));
+        }
+        self.start()?;
+        self.inner.flush()
+    }
+
+    fn start(&mut self)-> Result<(),Error>{
+        if !self.started{
+            self.started = true;
+            self.inner.write_all(self.encoding.bom())?;
+        }
+        Ok(())
+    }
+
+    fn write_text(&mut self,text: &str)-> Result<(),Error>{
+        let mut converted = String::with_capacity(text.len());
+        for c in text.chars(){
+            if c == '\n' && self.eol == Eol::Crlf && !self.last_cr{
+                converted.push('\r');
+            }
+            converted.push(c);
+            self.last_cr = c == '\r';
+        }
+        let bytes = match self.encoding.encode(&converted){
+            Some(bytes) => bytes,
+            None => return Err(self.unmappable(&converted)),
+        };
+        self.line += text.matches('\n').count();
+        self.start()?;
+        self.inner.write_all(&bytes)
+    }
+
+    /// the error naming the first char of `text` the encoding has no code for.
+    fn unmappable(&self,text: &str)-> Error{
+        let mut line = self.line;
+        let mut buffer = [0;4];
+        for c in text.chars(){
+            if self.encoding.encode(c.encode_utf8(&mut buffer)).is_none(){
+                return Error::new(ErrorKind::InvalidData,
+                    format!("line {}: '{}' (U+{:04X}) cannot be represented in {}",line,c,c as u32,self.encoding.name()));
+            }
+            if c == '\n'{
+                line += 1;
+            }
+        }
+        Error::new(ErrorKind::InvalidData,format!("text cannot be represented in {}",self.encoding.name()))
+    }
+}
+
+impl Write for EncodedWriter<'_>{
+    fn write(&mut self,buf: &[u8])-> Result<usize,Error>{
+        self.pending.extend_from_slice(buf);
+        let valid = match std::str::from_utf8(&self.pending){
+            Ok(_) => self.pending.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(Error::new(ErrorKind::InvalidData,"formatter wrote invalid utf-8")),
+        };
+        let rest = self.pending.split_off(valid);
+        let pending = std::mem::replace(&mut self.pending,rest);
+        // checked above.
+        self.write_text(std::str::from_utf8(&pending).unwrap())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self)-> Result<(),Error>{
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use std::io::Write;
+    use crate::encoding::{EncodedWriter,Encoding,Eol};
+
+    fn encode(text: &[&[u8]],encoding: Encoding,eol: Eol)-> Result<Vec<u8>,std::io::Error>{
+        let mut output = Vec::new();
+        let mut writer = EncodedWriter::new(&mut output,encoding,eol);
+        for part in text{
+            writer.write_all(part)?;
+        }
+        writer.finish()?;
+        Ok(output)
+    }
+
+    #[test]
+    fn encoded_writer_test(){
+        let text = "一\n二\r\n";
+        assert_eq!(encode(&[text.as_bytes()],Encoding::Utf8,Eol::Lf).unwrap(),text.as_bytes());
+        assert_eq!(encode(&[b"a\nb"],Encoding::Utf8Bom,Eol::Crlf).unwrap(),b"\xEF\xBB\xBFa\r\nb");
+        assert_eq!(encode(&[b"a\n"],Encoding::Utf16Le,Eol::Crlf).unwrap(),b"\xFF\xFEa\0\r\0\n\0");
+        // a char split between two writes.
+        let bytes = text.as_bytes();
+        assert_eq!(encode(&[&bytes[..1],&bytes[1..]],Encoding::Gbk,Eol::Crlf).unwrap(),b"\xD2\xBB\r\n\xB6\xFE\r\n");
+        assert_eq!(encode(&[],Encoding::Utf16Le,Eol::Lf).unwrap(),b"\xFF\xFE");
+
+        let error = encode(&["ok\n你好🙂".as_bytes()],Encoding::Gbk,Eol::Lf).unwrap_err();
+        assert_eq!(error.to_string(),"line 2: '🙂' (U+1F642) cannot be represented in gbk");
+    }
+}
@@ -0,0 +1,180 @@
+use crate::cc::{self,Formatter,TtmlTime};
+
+/// the settings a formatter may be built with. every format reads the ones it cares about.
+#[derive(Debug,Clone)]
+pub struct FormatOptions{
+    /// frame rate used by sub.
+    pub fps: f64,
+    pub ttml_time: TtmlTime,
+    pub lrc_end_tags: bool,
+    /// seconds of silence starting a new txt/md paragraph.
+    pub paragraph_gap: f64,
+    /// prefix every txt/md paragraph with its start time.
+    pub timestamps: bool,
+    /// an ass file whose header is used as template.
+    pub ass_style: Option<String>,
+    pub ass_play_res: Option<(u32,u32)>,
+}
+
+impl Default for FormatOptions{
+    fn default()->Self{
+        FormatOptions{
+            fps: 23.976,
+            ttml_time: TtmlTime::Clock,
+            lrc_end_tags: false,
+            paragraph_gap: 2.,
+            timestamps: false,
+            ass_style: None,
+            ass_play_res: None,
+        }
+    }
+}
+
+type Constructor = Box<dyn Fn(&FormatOptions)-> Box<dyn Formatter>>;
+
+/// a named output format and how to build its formatter.
+pub struct FormatEntry{
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// extension of the written files, without the dot.
+    pub ext: String,
+    pub description: String,
+    constructor: Constructor,
+}
+
+impl FormatEntry{
+    pub fn new<F>(name: &str,ext: &str,description: &str,constructor: F)->Self
+    where F: Fn(&FormatOptions)-> Box<dyn Formatter> + 'static{
+        FormatEntry{
+            name: name.to_string(),
+            aliases: Vec::new(),
+            ext: ext.to_string(),
+            description: description.to_string(),
+            constructor: Box::new(constructor),
+        }
+    }
+
+    /// another name accepted for the format.
+    pub fn alias(mut self,alias: &str)->Self{
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    pub fn create(&self,options: &FormatOptions)-> Box<dyn Formatter>{
+        (self.constructor)(options)
+    }
+
+    fn matches(&self,name: &str)-> bool{
+        self.name.eq_ignore_ascii_case(name) || self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+}
+
+/// the output formats known by name. `Registry::builtin()` holds every format of `cc`,
+/// `register` adds more.
+#[derive(Default)]
+pub struct Registry{
+    entries: Vec<FormatEntry>,
+}
+
+impl Registry{
+    /// a registry without any format.
+    pub fn new()->Self{
+        Registry{entries: Vec::new()}
+    }
+
+    /// a registry with every format of `cc`.
+    pub fn builtin()->Self{
+        let mut registry = Registry::new();
+        registry.register(FormatEntry::new("srt","srt","SubRip",|_| Box::new(cc::Srt::new())).alias("subrip"));
+        registry.register(FormatEntry::new("sub","sub","MicroDVD, frames at --fps",|options| Box::new(cc::Sub::new(options.fps))).alias("microdvd"));
+        registry.register(FormatEntry::new("ass","ass","Advanced SubStation Alpha",|options| {
+            let mut ass = cc::Ass::new();
+            if let Some(template) = &options.ass_style{
+                ass = ass.template(template.clone());
+            }
+            if let Some((x,y)) = options.ass_play_res{
+                ass = ass.play_res(x,y);
+            }
+            Box::new(ass)
+        }));
+        registry.register(FormatEntry::new("vtt","vtt","WebVTT",|_| Box::new(cc::Vtt::new())).alias("webvtt"));
+        registry.register(FormatEntry::new("bcc","bcc","bilibili cc json",|_| Box::new(cc::Bcc::new())));
+        registry.register(FormatEntry::new("ttml","ttml","Timed Text Markup Language",|options| Box::new(cc::Ttml::new(options.ttml_time))).alias("dfxp"));
+        registry.register(FormatEntry::new("sbv","sbv","YouTube SubViewer",|_| Box::new(cc::Sbv::new())));
+        registry.register(FormatEntry::new("srv3","srv3","YouTube timed text",|_| Box::new(cc::Srv3::new())));
+        registry.register(FormatEntry::new("lrc","lrc","lyrics",|options| Box::new(cc::Lrc::new(options.lrc_end_tags))));
+        registry.register(FormatEntry::new("txt","txt","plain text transcript",|options| Box::new(cc::Txt::new(options.paragraph_gap,options.timestamps))).alias("text"));
+        registry.register(FormatEntry::new("md","md","markdown transcript",|options| Box::new(cc::Md::new(options.paragraph_gap,options.timestamps))).alias("markdown"));
+        registry.register(FormatEntry::new("json","json","bccdc json interchange format",|_| Box::new(cc::Json::new())));
+        registry
+    }
+
+    /// add a format. a format of the same name is replaced, and the new one wins over aliases of the others.
+    pub fn register(&mut self,entry: FormatEntry){
+        match self.entries.iter().position(|x| x.name.eq_ignore_ascii_case(&entry.name)){
+            Some(index) => self.entries[index] = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// look a format up by name or alias, ignoring case.
+    pub fn find(&self,name: &str)-> Option<&FormatEntry>{
+        let name = name.trim();
+        self.entries.iter().find(|x| x.name.eq_ignore_ascii_case(name))
+            .or_else(|| self.entries.iter().rev().find(|x| x.matches(name)))
+    }
+
+    /// build the formatter of a format by name or alias.
+    pub fn create(&self,name: &str,options: &FormatOptions)-> Option<Box<dyn Formatter>>{
+        self.find(name).map(|entry| entry.create(options))
+    }
+
+    /// every format in the order they were registered.
+    pub fn entries(&self)-> &[FormatEntry]{
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use std::io::{Error,Write};
+    use crate::cc::{CcSubtitle,Formatter};
+    use crate::format::{Registry,FormatEntry,FormatOptions};
+
+    struct Count;
+
+    impl Formatter for Count{
+        fn ext(&self)->&str{
+            "count"
+        }
+
+        fn write(&mut self, writer: &mut dyn  Write, subtitle: &CcSubtitle)-> Result<(),Error>{
+            write!(writer,"{}",subtitle.lines.len())
+        }
+    }
+
+    #[test]
+    fn registry_test(){
+        assert!(Registry::default().entries().is_empty());
+        let mut registry = Registry::builtin();
+        let options = FormatOptions::default();
+        for entry in registry.entries(){
+            assert_eq!(entry.create(&options).ext(),entry.ext,"{}",entry.name);
+        }
+        assert_eq!(registry.find("WebVTT").unwrap().name,"vtt");
+        assert_eq!(registry.create("SRT",&options).unwrap().ext(),"srt");
+        assert!(registry.find("count").is_none());
+
+        registry.register(FormatEntry::new("count","count","number of cues",|_| Box::new(Count)).alias("n"));
+        let mut formatter = registry.create("n",&options).unwrap();
+        let mut buffer = Vec::new();
+        formatter.write(&mut buffer,&CcSubtitle::new("test",vec![])).unwrap();
+        assert_eq!(buffer,b"0");
+
+        // replacing a builtin keeps its place.
+        let index = registry.entries().iter().position(|x| x.name == "srt").unwrap();
+        registry.register(FormatEntry::new("srt","srt","custom",|_| Box::new(Count)));
+        assert_eq!(registry.entries()[index].description,"custom");
+        assert_eq!(registry.create("srt",&options).unwrap().ext(),"count");
+    }
+}
@@ -2,5 +2,8 @@
 pub mod lookup;
 pub mod cc;
 pub mod bili;
+pub mod zh;
+pub mod encoding;
+pub mod format;
 
 
@@ -3,7 +3,7 @@ use url::{Url};
 use std::path::Path;
 use std::error::Error;
 use serde_json::{Value,Deserializer};
-use crate::cc::{CcSubtitle,Line};
+use crate::cc::{self,BccStyle,CcSubtitle,Line,VideoMeta,Timestamp};
 
 use crate::bili;
 
@@ -31,8 +31,35 @@ pub struct VideoPage{
     pub subtitles: Vec<CcSubtitle>,
 }
 
+/// a page with its subtitles, or why they could not be downloaded.
+pub type PageResult = Result<VideoPage,Box<dyn Error>>;
 
 
+
+fn json_to_style(v: &Value)-> Option<BccStyle>{
+    let keys = ["font_size","font_color","background_alpha","background_color","Stroke"];
+    if keys.iter().all(|key| v[key].is_null()){
+        return None;
+    }
+    let mut style = BccStyle::default();
+    if let Some(font_size) = v["font_size"].as_f64(){
+        style.font_size = font_size;
+    }
+    if let Some(font_color) = v["font_color"].as_str(){
+        style.font_color = font_color.to_string();
+    }
+    if let Some(background_alpha) = v["background_alpha"].as_f64(){
+        style.background_alpha = background_alpha;
+    }
+    if let Some(background_color) = v["background_color"].as_str(){
+        style.background_color = background_color.to_string();
+    }
+    if let Some(stroke) = v["Stroke"].as_str(){
+        style.stroke = stroke.to_string();
+    }
+    Some(style)
+}
+
 pub fn json_to_subtitle(name: &str,content: &str)-> Result<CcSubtitle,Box<dyn Error>> {
     let r = serde_json::from_str(content);
     if let Err(_) = r{
@@ -48,23 +75,40 @@ pub fn json_to_subtitle(name: &str,content: &str)-> Result<CcSubtitle,Box<dyn Er
         let content= obj["content"].as_str().ok_or::<Box<dyn Error>>(ERR_MSG.into())?.to_string();
         let start = obj["from"].as_f64().ok_or::<Box<dyn Error>>(ERR_MSG.into())?;
         let end = obj["to"].as_f64().ok_or::<Box<dyn Error>>(ERR_MSG.into())?;
-        Ok(Line{content,start,end})
+        let mut line = Line::new(Timestamp::from_secs_f64(start),Timestamp::from_secs_f64(end),content);
+        line.location = obj["location"].as_u64().map(|x| x as u8);
+        Ok(line)
     }).collect();
     
-    Ok(CcSubtitle{
-        name: name.to_string(),
-        lan: None,
-        lan_doc: None,
-        lines: lines?,
-    })
+    let mut subtitle = CcSubtitle::new(name,lines?);
+    subtitle.style = json_to_style(&v);
+    Ok(subtitle)
+
+}
 
+/// pick a parser by looking at the content. falls back to srt.
+pub fn content_to_subtitle(name: &str,content: &str)-> Result<CcSubtitle,Box<dyn Error>> {
+    let head = content.trim_start_matches('\u{feff}').trim_start();
+    if head.starts_with('{'){
+        if cc::Json::detect(head){
+            return Ok(cc::Json::parse(name,head)?);
+        }
+        return json_to_subtitle(name,content);
+    }
+    if head.starts_with("WEBVTT"){
+        return Ok(cc::Vtt::parse(name,content)?);
+    }
+    if head.starts_with('['){
+        return Ok(cc::Ass::parse(name,content)?);
+    }
+    Ok(cc::Srt::parse(name,content)?)
 }
 
 pub fn lookup_file(path: &Path)-> Result<CcSubtitle,Box<dyn Error>> {
 
     let name = path.file_stem().unwrap().to_str().unwrap();
     let content=fs::read_to_string(path)?;
-    json_to_subtitle(name,&content)
+    content_to_subtitle(name,&content)
 
 }
 
@@ -82,7 +126,8 @@ pub fn lookup_cc_api(url: &Url) -> Result<CcSubtitle,Box<dyn Error>>{
 
 }
 
-fn find_id(ep_html: &str)-> Option<(String,u64)>{
+/// the video embedded in a page.
+fn find_id(ep_html: &str)-> Option<VideoMeta>{
 
     let flag = "window.__INITIAL_STATE__=";
     
@@ -99,14 +144,23 @@ fn find_id(ep_html: &str)-> Option<(String,u64)>{
                 let bvid= &ep_info["bvid"].as_str();
                 let cid = &ep_info["cid"].as_u64();
                 if bvid.is_some() && cid.is_some(){
-                   return Some((bvid.unwrap().to_string(),cid.unwrap())); 
+                   let mut meta = VideoMeta::new(bvid.unwrap(),cid.unwrap(),1);
+                   // episodes count their duration in milliseconds.
+                   meta.duration = ep_info["duration"].as_i64().map(Timestamp::from_millis);
+                   meta.title = ep_info["share_copy"].as_str().map(String::from);
+                   meta.part = ep_info["long_title"].as_str().map(String::from);
+                   meta.source = ep_info["link"].as_str().map(String::from);
+                   return Some(meta); 
                 }
             }else if let Value::Object(video_data) = &json["videoData"]{
                 let bvid= &video_data["bvid"].as_str();
                 let cid = &video_data["cid"].as_u64();
                 
                 if bvid.is_some() && cid.is_some(){
-                   return Some((bvid.unwrap().to_string(),cid.unwrap())); 
+                   let mut meta = VideoMeta::new(bvid.unwrap(),cid.unwrap(),1);
+                   meta.duration = video_data["duration"].as_f64().map(Timestamp::from_secs_f64);
+                   meta.title = video_data["title"].as_str().map(String::from);
+                   return Some(meta); 
                 }
             }
         }
@@ -116,8 +170,8 @@ fn find_id(ep_html: &str)-> Option<(String,u64)>{
 
 }
 
-fn get_subtitles(bvid: &str,cid: u64,page: u32)-> Result<Vec<CcSubtitle>,Box<dyn Error>>{
-    let list= bili::get_subtitle_list(&bvid,&cid)?;
+fn get_subtitles(meta: &VideoMeta)-> Result<Vec<CcSubtitle>,Box<dyn Error>>{
+    let list= bili::get_subtitle_list(&meta.bvid,&meta.cid)?;
 
     let mut result = Vec::new();
     for info in list {
@@ -126,10 +180,11 @@ fn get_subtitles(bvid: &str,cid: u64,page: u32)-> Result<Vec<CcSubtitle>,Box<dyn
                 Ok(mut cc)=> {
                     cc.lan = Some(info.lan);
                     cc.lan_doc = Some(info.lan_doc);
+                    cc.meta = Some(meta.clone());
                     result.push(cc)
                 },
                 Err(e)=> {
-                    eprintln!("fail to download {}-p{} subtitle: {} cause: {}",bvid,page,info.lan_doc,e);
+                    eprintln!("fail to download {}-p{} subtitle: {} cause: {}",meta.bvid,meta.p,info.lan_doc,e);
                 }
             }
         }
@@ -141,42 +196,50 @@ fn get_subtitles(bvid: &str,cid: u64,page: u32)-> Result<Vec<CcSubtitle>,Box<dyn
 pub fn lookup_ep_id(id: &str)-> Result<Vec<CcSubtitle>,Box<dyn Error>>{
     let content=bili::get_ep_html(id)?;
 
-    let (bvid,cid) = find_id(&content).ok_or::<Box<dyn Error>>(format!("unable find bvid and cid by {}",id).into())?;
+    let meta = find_id(&content).ok_or::<Box<dyn Error>>(format!("unable find bvid and cid by {}",id).into())?;
 
-    get_subtitles(&bvid,cid,1)
+    get_subtitles(&meta)
     
 }
 
-pub fn lookup_video_id(id: &str,interested: Vec<Page>)-> Result<Vec<VideoPage>,Box<dyn Error>>{
+/// the subtitles of every interested page. a page failing to download is an `Err` in the list.
+pub fn lookup_video_id(id: &str,interested: Vec<Page>)-> Result<Vec<PageResult>,Box<dyn Error>>{
     let mut bvid = id.to_string();
     if bvid.starts_with("av"){
         let aid = id[2..].parse::<u64>()?;
         bvid = bili::av_to_bv(&aid);
     }
 
-    let page_list = bili::get_page_list(&bvid.trim())?;
-
-    let vsubs : Vec<VideoPage>= page_list.iter()
+    let bvid = bvid.trim();
+    // the view api also knows the title, the page list is enough to download.
+    let (title,page_list) = match bili::get_video_info(bvid){
+        Ok(info) => (Some(info.title),info.pages),
+        Err(e) => {
+            eprintln!("fail to get video info of {}. cause: {}",bvid,e);
+            (None,bili::get_page_list(bvid)?)
+        },
+    };
+
+    let vsubs = page_list.iter()
         .filter(|page| interested.iter().any(|range| range.test(&page.page)))
         .map(|page| { 
+            let mut meta = VideoMeta::new(bvid,page.cid,page.page);
+            meta.duration = Some(Timestamp::from_millis(page.duration as i64 * 1000));
+            meta.title = title.clone();
+            meta.part = Some(page.part.clone());
             
-            let r = get_subtitles(&bvid,page.cid,page.page)
-                .map(|subs| VideoPage{p: page.page,subtitles: subs});
-
-            if let Err(ref e) = r{
-                eprintln!("fail to get subtitle list. cause: {}",e);
-            }
-            r
+            get_subtitles(&meta)
+                .map(|subs| VideoPage{p: page.page,subtitles: subs})
+                .map_err(|e| format!("fail to get subtitle list of p{}. cause: {}",page.page,e).into())
 
         })
-        .filter(|r| r.is_ok())
-        .map(|r| r.unwrap())
         .collect();
 
     Ok(vsubs)    
 }
 
-pub fn lookup_media_id(id:&str,interested: Vec<Page>)-> Result<Vec<VideoPage>,Box<dyn Error>>{
+/// the subtitles of every interested episode. an episode failing to download is an `Err` in the list.
+pub fn lookup_media_id(id:&str,interested: Vec<Page>)-> Result<Vec<PageResult>,Box<dyn Error>>{
     let season_id = bili::get_season_id(id)?;
     
     let episodes = bili::get_season_episodes(&season_id)?;
@@ -186,15 +249,15 @@ pub fn lookup_media_id(id:&str,interested: Vec<Page>)-> Result<Vec<VideoPage>,Bo
         .map(|(index,ep)| {
             let bvid = bili::av_to_bv(&ep.aid);
             let p = (index+1) as u32;
-            let r = get_subtitles(&bvid,ep.cid,p)
-                .map(|subs| VideoPage{p:p,subtitles: subs});
-            if let Err(ref e) = r{
-                eprintln!("fail to get subtitle. cause: {}",e);
-            }
-            r
+            // every episode is the first page of its own video.
+            let mut meta = VideoMeta::new(&bvid,ep.cid,1);
+            meta.duration = ep.duration.map(|x| Timestamp::from_millis(x as i64));
+            meta.part = ep.long_title.clone();
+            meta.source = ep.share_url.clone();
+            get_subtitles(&meta)
+                .map(|subs| VideoPage{p:p,subtitles: subs})
+                .map_err(|e| format!("fail to get subtitle of ep{}. cause: {}",p,e).into())
         })
-        .filter(|r| r.is_ok())
-        .map(|r| r.unwrap())
         .collect();
     Ok(r)
 }
@@ -204,6 +267,7 @@ mod tests{
     use tempfile::NamedTempFile;
     use std::fs;
     use crate::lookup;
+    use crate::cc::{self,Formatter,Timestamp};
     #[test]
     fn lookup_file_test(){
         let json= "{\"body\":[{\"content\":\"花蕾 石屑 又一輪循環\",\"from\":1341.19,\"location\":2,\"to\":1343.27}]}";
@@ -214,8 +278,38 @@ mod tests{
         assert_eq!(temppath.file_name().unwrap().to_str(),Some(&subtitle.name[..]));
         let line= &subtitle.lines[0];
         assert_eq!("花蕾 石屑 又一輪循環",&line.content);
-        assert_eq!(1341.19,line.start);
-        assert_eq!(1343.27,line.end);
+        assert_eq!(Timestamp::from_millis(1_341_190),line.start);
+        assert_eq!(Timestamp::from_millis(1_343_270),line.end);
+        tempfile.close().expect("");
+    }
+
+    #[test]
+    fn bcc_round_trip_test(){
+        let json= "{\"font_size\":0.6,\"font_color\":\"#FF0000\",\"background_alpha\":0.2,\"background_color\":\"#000000\",\"Stroke\":\"none\",\"body\":[{\"content\":\"花蕾 石屑 又一輪循環\",\"from\":1341.19,\"location\":8,\"to\":1343.27}]}";
+        let subtitle = lookup::json_to_subtitle("test",json).unwrap();
+        let mut output = Vec::new();
+        cc::Bcc::new().write(&mut output,&subtitle).unwrap();
+
+        let result = lookup::json_to_subtitle("test",std::str::from_utf8(&output).unwrap()).unwrap();
+        assert_eq!(result.style,subtitle.style);
+        assert_eq!(result.style.unwrap().font_size,0.6);
+        let line= &result.lines[0];
+        assert_eq!("花蕾 石屑 又一輪循環",&line.content);
+        assert_eq!(Timestamp::from_millis(1_341_190),line.start);
+        assert_eq!(Timestamp::from_millis(1_343_270),line.end);
+        assert_eq!(Some(8),line.location);
+    }
+
+    #[test]
+    fn lookup_srt_file_test(){
+        let srt = "1\n00:22:21,190 --> 00:22:23,270\n花蕾 石屑 又一輪循環\n";
+        let tempfile=NamedTempFile::new().unwrap();
+        fs::write(&tempfile,srt).expect("fail to write tempfile");
+        let subtitle = crate::lookup::lookup_file(tempfile.path()).unwrap();
+        let line= &subtitle.lines[0];
+        assert_eq!("花蕾 石屑 又一輪循環",&line.content);
+        assert_eq!(Timestamp::from_millis(1_341_190),line.start);
+        assert_eq!(Timestamp::from_millis(1_343_270),line.end);
         tempfile.close().expect("");
     }
 
@@ -223,14 +317,20 @@ mod tests{
     fn find_id_test(){
         let content = "</script><script>window.__INITIAL_STATE__={\"epInfo\":{\"aid\":937924663,\"badge\":\"会员\",\"badge_info\":{\"bg_color\":\"#FB7299\",\"bg_color_night\":\"#BB5B76\",\"text\":\"会员\"},\"badge_type\":0,\"bvid\":\"BV1zT4y1v7kC\",\"cid\":569612278,\"cover\":\"\\u002F\\u002Fi0.hdslb.com\\u002Fbfs\\u002Farchive\\u002Ff5e5f123aef7399156a6fe74d4cb7aaf97604a20.png\",\"dimension\":{\"height\":1080,\"rotate\":0,\"width\":1920},\"duration\":1421000,\"from\":\"bangumi\",\"id\":475899,\"is_view_hide\":false,\"link\":\"https:\\u002F\\u002Fwww.bilibili.com\\u002Fbangumi\\u002Fplay\\u002Fep475899\",\"long_title\":\"孔明 施展計謀\",\"pub_time\":1649340000,\"pv\":0,\"release_date\":\"\",\"rights\":{\"allow_demand\":0,\"allow_dm\":1,\"allow_download\":0,\"area_limit\":0},\"share_copy\":\"《派對咖孔明（僅限港澳台地區）》第2话 孔明 施展計謀\",\"share_url\":\"https:\\u002F\\u002Fwww.bilibili.com\\u002Fbangumi\\u002Fplay\\u002Fep475899\",\"short_link\":\"https:\\u002F\\u002Fb23.tv\\u002Fep475899\",\"status\":13,\"subtitle\":\"已观看18万次\",\"title\":\"2\",\"vid\":\"\",\"loaded\":true,\"badgeType\":0,\"badgeColor\":\"#FB7299\",\"epStatus\":13,\"titleFormat\":\"第2话\",\"longTitle\":\"孔明 施展計謀\",\"sectionType\":0,\"releaseDate\":\"\",\"skip\":{},\"stat\":{},\"orderSectionIds\":[],\"hasNext\":false,\"hasSkip\":false,\"i\":1}};(function(){vars;(s=document.currentScript||document.scripts[document.scripts.length-1]).parentNode.removeChild(s);}());</script><scripttype=\"text/javascript\">";
 
-        assert_eq!(lookup::find_id(content),Some((String::from("BV1zT4y1v7kC"),569612278)));
+        let meta = lookup::find_id(content).unwrap();
+        assert_eq!((meta.bvid.as_str(),meta.cid,meta.duration),("BV1zT4y1v7kC",569612278,Some(Timestamp::from_millis(1_421_000))));
+        assert_eq!(meta.part.as_deref(),Some("孔明 施展計謀"));
+        assert_eq!(meta.url(),"https://www.bilibili.com/bangumi/play/ep475899");
     }
 
     #[test]
     fn find_id_test1(){
         let content = "</script><script>window.__INITIAL_STATE__={\"aid\":1703355805,\"bvid\":\"BV1mT42127CQ\",\"p\":1,\"episode\":\"\",\"videoData\":{\"bvid\":\"BV1mT42127CQ\",\"aid\":1703355805,\"videos\":1,\"tid\":201,\"tname\":\"科学科普\",\"copyright\":2,\"pic\":\"http://i1.hdslb.com/bfs/archive/4786b7bc4a268ea0bb31bf66be6aa6eee1a04c33.png\",\"title\":\"【地理】直布罗陀海峡形成与地中海的滔天洪水\",\"pubdate\":1713668408,\"ctime\":1713622289,\"desc\":\"530万年前，一次大冰期导致了海平面下降，地中海与大西洋沟通消失，75%的地中海蒸发演变为沙漠。当冰期结束海平面再次上升,形成了直布罗陀瀑布,从最初涓流细流到冲刷出了一道越来越深的缺口用了数千年，最后，当直布罗陀变成了亚马逊河流量的1000倍的巨大瀑布时，仅用几个月将地中海90%的面积填满。【地址】https://youtu.be/_RSPgIcnRN0\",\"desc_v2\":[{\"raw_text\":\"530万年前，一次大冰期导致了海平面下降，地中海与大西洋沟通消失，75%的地中海蒸发演变为沙漠。当冰期结束海平面再次上升,形成了直布罗陀瀑布,从最初涓流细流到冲刷出了一道越来越深的缺口用了数千年，最后，当直布罗陀变成了亚马逊河流量的1000倍的巨大瀑布时，仅用几个月将地中海90%的面积填满。【地址】https://youtu.be/_RSPgIcnRN0\",\"type\":1,\"biz_id\":0}],\"state\":0,\"duration\":794,\"argue_info\":{\"argue_msg\":\"\",\"argue_type\":0,\"argue_link\":\"\"},\"dynamic\":\"\",\"cid\":1514080023,\"dimension\":{\"width\":1280,\"height\":720,\"rotate\":0},\"season_id\":2545199,\"premiere\":null,\"teenage_mode\":0,\"is_chargeable_season\":false,\"is_story\":false,\"is_upower_exclusive\":false,\"is_upower_play\":false,\"is_upower_preview\":false,\"enable_vt\":0,\"vt_display\":\"\",\"no_cache\":false,\"is_season_display\":true,\"user_garb\":{\"url_image_ani_cut\":\"\"},\"honor_reply\":{},\"like_icon\":\"\",\"need_jump_bv\":false,\"disable_show_up_info\":false,\"is_story_play\":1},\"isCollection\":0,\"sectionsInfo\":{\"id\":2545199,\"title\":\"地理* 海陆变迁与板块构造\",\"cover\":\"https://s1.hdslb.com/bfs/templar/york-static/viedeo_material_default.png\",\"mid\":224888695,\"intro\":\"\",\"sign_state\":0,\"attribute\":140,\"sections\":[],\"stat\":{\"season_id\":2545199,\"view\":1506470,\"danmaku\":8664,\"reply\":5305,\"fav\":25040,\"coin\":7379,\"share\":3511,\"now_rank\":0,\"his_rank\":0,\"like\":38670,\"vt\":0,\"vv\":0},\"ep_count\":42,\"season_type\":1,\"is_pay_season\":false,\"enable_vt\":0},\"playedSectionId\":[],\"sections\":[],\"staffData\":[],\"isClient\":false,\"error\":{},\"player\":null,\"playurl\":{},\"user\":{},\"cidMap\":{\"1703355805\":{\"aid\":1703355805,\"bvid\":\"BV1mT42127CQ\",\"cids\":{\"1\":1514080023}},\"BV1mT42127CQ\":{\"aid\":1703355805,\"bvid\":\"BV1mT42127CQ\",\"cids\":{\"1\":1514080023}}},\"isRecAutoPlay\":false,\"continuousPlay\":true,\"autoPlayNextVideo\":null,\"elecFullInfo\":{},\"emergencyBan\":{\"no_like\":false,\"no_coin\":false,\"no_fav\":false,\"no_share\":false},\"isModern\":true,\"playerReloadOrigin\":\"\",\"queryTags\":[],\"nanoTheme\":{\"bpx-primary-color\":\"var(--brand_blue)\",\"bpx-fn-color\":\"var(--brand_blue)\",\"bpx-fn-hover-color\":\"var(--brand_blue)\",\"bpx-box-shadow\":\"var(--bg3)\",\"bpx-dmsend-switch-icon\":\"var(--text2)\",\"bpx-dmsend-hint-icon\":\"var(--graph_medium)\",\"bpx-aux-header-icon\":\"var(--graph_icon)\",\"bpx-aux-float-icon\":\"var(--graph_icon)\",\"bpx-aux-block-icon\":\"var(--text3)\",\"bpx-dmsend-info-font\":\"var(--text2)\",\"bpx-dmsend-input-font\":\"var(--text1)\",\"bpx-dmsend-hint-font\":\"var(--text3)\",\"bpx-aux-header-font\":\"var(--text1)\",\"bpx-aux-footer-font\":\"var(--text2)\",\"bpx-aux-footer-font-hover\":\"var(--text1)\",\"bpx-aux-content-font1\":\"var(--text1)\",\"bpx-aux-content-font2\":\"var(--text2)\",\"bpx-aux-content-font3\":\"var(--text2)\",\"bpx-aux-content-font4\":\"var(--text3)\",\"bpx-aux-content-font5\":\"var(--text3)\",\"bpx-dmsend-main-bg\":\"var(--bg1)\",\"bpx-dmsend-input-bg\":\"var(--bg3)\",\"bpx-aux-header-bg\":\"var(--graph_bg_regular)\",\"bpx-aux-footer-bg\":\"var(--graph_bg_regular)\",\"bpx-aux-content-bg\":\"var(--bg1)\",\"bpx-aux-button-bg\":\"var(--bg3)\",\"bpx-aux-button-disabled-bg\":\"var(--graph_bg_thin)\",\"bpx-aux-float-bg\":\"var(--bg1_float)\",\"bpx-aux-float-hover-bg\":\"var(--graph_medium)\",\"bpx-aux-cover-bg\":\"var(--graph_weak)\",\"bpx-dmsend-border\":\"var(--bg3)\",\"bpx-aux-float-border\":\"var(--line_light)\",\"bpx-aux-line-border\":\"var(--line_regular)\",\"bpx-aux-input-border\":\"var(--line_regular)\"},\"enable_vt\":0,\"defaultWbiKey\":{\"wbiImgKey\":\"2590160e9f5142d4a501feda0490f3bd\",\"wbiSubKey\":\"34ba9c5c4a824b368e9c053be34016bd\"},\"bmpDefDomain\":\"\",\"loadingRcmdTabData\":false,\"rcmdTabData\":{\"tab_name\":\"全部\",\"archives\":[],\"has_more\":false},\"rcmdTabNames\":[\"科学\",\"科普\",\"灾难\",\"地理\",\"古地理\",\"地质巨变\",\"洪水\",\"地中海\"],\"currentRcmdTab\":{\"tab_name\":\"全部\",\"tab_order\":0,\"tab_type\":1}}";
 
-        assert_eq!(lookup::find_id(content),Some((String::from("BV1mT42127CQ"),1514080023)));
+        let meta = lookup::find_id(content).unwrap();
+        assert_eq!((meta.bvid.as_str(),meta.cid,meta.duration),("BV1mT42127CQ",1514080023,Some(Timestamp::from_millis(794_000))));
+        assert_eq!(meta.title.as_deref(),Some("【地理】直布罗陀海峡形成与地中海的滔天洪水"));
+        assert_eq!(meta.url(),"https://www.bilibili.com/video/BV1mT42127CQ?p=1");
     }
 }
 
@@ -6,14 +6,34 @@ use std::error::Error;
 use bccdc::cc;
 use bccdc::cc::Formatter;
 use bccdc::lookup;
+use bccdc::zh;
+use bccdc::encoding::{self,Encoding,Eol};
+use bccdc::format::{self,FormatOptions};
 
 use bccdc::bili;
 
+/// a source anchor and the time it should be moved to.
+type ResyncPoint = (cc::Anchor,cc::Timestamp);
+
 struct Config{
     work_dir: PathBuf,
     format: String,
     doc: bool,
     mixed: bool,
+    format_options: FormatOptions,
+    bilingual: Option<(String,String)>,
+    shift: Option<i64>,
+    scale: Option<f64>,
+    fps_conversion: Option<(f64,f64)>,
+    resync: Option<(ResyncPoint,ResyncPoint)>,
+    normalize: Option<cc::NormalizeOptions>,
+    reflow: Option<cc::ReflowOptions>,
+    convert_zh: Option<zh::ZhVariant>,
+    check: bool,
+    check_options: cc::CheckOptions,
+    encoding: Encoding,
+    eol: Eol,
+    sidecar: bool,
 }
 
 impl Config{
@@ -26,16 +46,87 @@ impl Config{
             name.push_str(lan);
         }
     }
+
+    /// convert and retime the subtitle before it is written.
+    /// resync goes first so `@<cue index>` anchors count the cues as they were loaded.
+    fn transform(&self, sub: &mut cc::CcSubtitle)-> Result<(),Box<dyn Error>>{
+        if let Some((a,b)) = self.resync{
+            sub.resync(a,b).map_err(|e| format!("fail to resync: {}",e))?;
+        }
+        if let Some((from,to)) = self.fps_conversion{
+            sub.convert_fps(from,to);
+        }
+        if let Some(factor) = self.scale{
+            sub.scale(factor);
+        }
+        if let Some(ms) = self.shift{
+            sub.shift(ms);
+        }
+        if let Some(options) = &self.normalize{
+            sub.normalize(options);
+        }
+        if let Some(options) = &self.reflow{
+            sub.reflow(options);
+        }
+        Ok(())
+    }
+
+    /// convert the chinese (or unlabeled) subtitles, the language label in their names follows the new variant.
+    /// a track already in the variant is left as it is, and so is one whose new name is taken,
+    /// e.g. `ai-zh` next to a `zh-Hans` track, as it would overwrite the other file.
+    fn convert_zh(&self, subs: &mut [cc::CcSubtitle], variant: zh::ZhVariant){
+        for index in 0..subs.len(){
+            let sub = &subs[index];
+            if sub.lan.as_deref().is_some_and(|lan| !lan.contains("zh") || lan.eq_ignore_ascii_case(variant.lan())){
+                continue;
+            }
+            let (lan,lan_doc) = sub.zh_labels(variant);
+            let (old,new) = if !self.doc { (&sub.lan,lan) }else{ (&sub.lan_doc,lan_doc) };
+            let mut name = sub.name.clone();
+            if let Some(old) = old{
+                if let Some(i) = name.rfind(old.as_str()){
+                    name.replace_range(i..i+old.len(),&new);
+                }
+            }
+            if subs.iter().enumerate().any(|(i,other)| i != index && other.name == name){
+                eprintln!("{}: {} already exists. skip converting",sub.name,name);
+                continue;
+            }
+            let sub = &mut subs[index];
+            sub.convert_zh(variant);
+            sub.name = name;
+        }
+    }
+
+    /// merge the two `--bilingual` tracks of a video page into one subtitle.
+    fn merge_bilingual(&self, subs: Vec<cc::CcSubtitle>)-> Vec<cc::CcSubtitle>{
+        let Some((primary,secondary)) = &self.bilingual else{
+            return subs;
+        };
+        let find = |lan: &str| subs.iter().position(|sub| sub.lan.as_deref().is_some_and(|x| x.eq_ignore_ascii_case(lan)));
+        match (find(primary),find(secondary)){
+            (Some(p),Some(s)) => vec![subs[p].bilingual(&subs[s])],
+            _ => {
+                if let Some(meta) = subs.first().and_then(|sub| sub.meta.as_ref()){
+                    eprintln!("{}-p{} has no {} and {} subtitle. skip merging",meta.bvid,meta.p,primary,secondary);
+                }
+                subs
+            },
+        }
+    }
 }
 
 struct Context<'a>{
     dir: Option<&'a str>,
     subtitles: Vec<cc::CcSubtitle>,
+    /// the number of inputs, pages or episodes that failed to load.
+    failures: usize,
 }
 
 fn print_helps(){
 
     println!("Usage: bccdc [option..] <avid/bvid/mdid/epid/bcc_url/bcc_file>
+       bccdc check [option..] <avid/bvid/mdid/epid/bcc_url/bcc_file>
 
 Examples:
     bccdc -d downloads/ --header 'cookie:value' BV1mT42127CQ
@@ -44,11 +135,37 @@ Examples:
     bccdc -d downloads/ ep475901
     bccdc -d downloads/ subtitle.json
     bccdc --mixed -d dwonloads/ ep475901 BV1ns411D7NJ 3-4 md28237168 9 subtitle.json
+    bccdc -c srt,ass,vtt -d downloads/ BV1ns411D7NJ # write every subtitle in three formats
+    bccdc check --max-cps 15 BV1mT42127CQ # report broken cues instead of writing files, exit with 1 on errors
 
 Options:
     -d <directory> specify the output directory
-    -c <srt/ass/vtt> specify the subtitle format to convert. default: srt 
+    -c <format>[,<format>..] specify the subtitle formats to convert, e.g. srt/sub/ass/vtt/bcc/ttml/sbv/srv3/lrc/txt/md/json. default: srt
+    --list-formats list every subtitle format of -c and exit
     --doc use language_name as filename instead of language_tag. (take effect while downloading with bvid/epid)
+    --ttml-time <clock/ticks> time expression used by ttml. default: clock
+    --lrc-end-tags append a <mm:ss.xx> tag marking the end of each lrc line
+    --paragraph-gap <seconds> start a new txt/md paragraph after this much silence. default: 2
+    --timestamps prefix every txt/md paragraph with its start time
+    --fps <fps> frame rate used by sub. default: 23.976
+    --ass-style <file> use the [Script Info] and [V4+ Styles] sections of file as ass header
+    --ass-playres <width>x<height> set PlayResX/PlayResY of ass
+    --bilingual <lan>,<lan> merge two language tracks into one subtitle, e.g. zh-Hans,en. (take effect while downloading with bvid/epid/mdid)
+    --shift <ms> delay subtitles by ms milliseconds. negative value brings them forward
+    --scale <factor> multiply every time by factor
+    --fps-from <fps> --fps-to <fps> retime subtitles of a fps-from video for the same video played at fps-to
+    --resync <anchor>=<time>,<anchor>=<time> linearly remap times so both anchors land on their times. anchor is a time or @<cue index>. applied before --shift/--scale/--fps-from
+    --normalize sort cues, collapse repeated text, resolve overlaps and enforce minimum duration and gap
+    --min-duration <ms> minimum cue duration of --normalize. default: 700
+    --min-gap <ms> minimum gap between cues of --normalize. default: 80
+    --merge-overlaps let --normalize merge overlapping cues instead of cutting them
+    --wrap <width> rewrap lines to width columns. cjk chars count as two
+    --max-lines <n> split cues wrapping into more than n lines. (take effect with --wrap) default: 2
+    --convert-zh <hans/hant/tw/hk> convert chinese subtitles to simplified, traditional, taiwan or hong kong chinese
+    --max-cps <n> chars per second above which check reports a cue. default: 20
+    --encoding <utf8/utf8-bom/utf16le/gbk> text encoding of written files. default: utf8
+    --eol <lf/crlf> line ending of written files. default: lf
+    --sidecar write <name>.meta.json describing the source video next to every subtitle
     --mixed allow pass mixed arguments
     --proxy <http://host:port> use proxy
     --header <key:value> pass custom header to server"
@@ -57,11 +174,37 @@ Options:
     process::exit(0);
 }
 
-fn parse_args(args: &mut std::env::Args)-> Result<(Config,Vec<String>),Box<dyn Error>> {
+fn print_formats(registry: &format::Registry){
+    for entry in registry.entries(){
+        let aliases = if entry.aliases.is_empty() { String::new() } else { format!(" (also {})",entry.aliases.join(", ")) };
+        println!("{:<6} .{:<6} {}{}",entry.name,entry.ext,entry.description,aliases);
+    }
+
+    process::exit(0);
+}
+
+fn parse_args(args: &mut std::env::Args,registry: &format::Registry)-> Result<(Config,Vec<String>),Box<dyn Error>> {
     let mut work_dir = std::env::current_dir().expect("fail to get pwd.");
     let mut format= String::from("srt");
     let mut doc= false;
     let mut mixed = false;
+    let mut format_options = FormatOptions::default();
+    let mut bilingual: Option<(String,String)> = None;
+    let mut shift: Option<i64> = None;
+    let mut scale: Option<f64> = None;
+    let mut fps_from: Option<f64> = None;
+    let mut fps_to: Option<f64> = None;
+    let mut resync = None;
+    let mut normalize = false;
+    let mut normalize_options = cc::NormalizeOptions::default();
+    let mut wrap: Option<usize> = None;
+    let mut max_lines = 2;
+    let mut convert_zh: Option<zh::ZhVariant> = None;
+    let mut check = false;
+    let mut check_options = cc::CheckOptions::default();
+    let mut encoding = Encoding::Utf8;
+    let mut eol = Eol::Lf;
+    let mut sidecar = false;
     let mut proxy: Option<String> = None;
     let mut headers: HashMap<String,Vec<String>> = HashMap::new();
     args.next();
@@ -90,12 +233,115 @@ fn parse_args(args: &mut std::env::Args)-> Result<(Config,Vec<String>),Box<dyn E
             "-c" =>{
                format = args.next().ok_or("-c requires parameter")?;
             },
+            "--list-formats" => print_formats(registry),
+            "--ttml-time" =>{
+                let time = args.next().ok_or("--ttml-time requires parameter")?;
+                format_options.ttml_time = match time.as_str() {
+                    "clock" => cc::TtmlTime::Clock,
+                    "ticks" => cc::TtmlTime::Ticks(10_000_000),
+                    other => return Err(format!("unsupported ttml time expression {}",other).into()),
+                };
+            },
+            "--lrc-end-tags" =>{
+                format_options.lrc_end_tags = true;
+            },
+            "--paragraph-gap" =>{
+                let gap = args.next().ok_or("--paragraph-gap requires parameter")?;
+                format_options.paragraph_gap = gap.parse::<f64>().map_err(|_| format!("invalid paragraph gap {}",gap))?;
+            },
+            "--timestamps" =>{
+                format_options.timestamps = true;
+            },
+            "--fps" =>{
+                let value = args.next().ok_or("--fps requires parameter")?;
+                format_options.fps = value.parse::<f64>().ok().filter(|x| *x>0.).ok_or(format!("invalid fps {}",value))?;
+            },
+            "--ass-style" =>{
+                let p = args.next().ok_or("--ass-style requires parameter")?;
+                format_options.ass_style = Some(fs::read_to_string(&p).map_err(|e| format!("fail to read {}: {}",p,e))?);
+            },
+            "--ass-playres" =>{
+                let value = args.next().ok_or("--ass-playres requires parameter")?;
+                let res = value.split_once(['x','X'])
+                    .and_then(|(x,y)| Some((x.trim().parse::<u32>().ok()?,y.trim().parse::<u32>().ok()?)))
+                    .ok_or(format!("--ass-playres requires pattern <width>x<height>. but found {}",value))?;
+                format_options.ass_play_res = Some(res);
+            },
+            "--bilingual" =>{
+                let value = args.next().ok_or("--bilingual requires parameter")?;
+                let (primary,secondary) = value.split_once(',').ok_or("--bilingual requires pattern <lan>,<lan>")?;
+                bilingual = Some((primary.trim().to_string(),secondary.trim().to_string()));
+            },
+            "--shift" =>{
+                let value = args.next().ok_or("--shift requires parameter")?;
+                shift = Some(value.parse::<i64>().map_err(|_| format!("invalid shift {}",value))?);
+            },
+            "--scale" =>{
+                let value = args.next().ok_or("--scale requires parameter")?;
+                scale = Some(value.parse::<f64>().ok().filter(|x| *x>0.).ok_or(format!("invalid scale {}",value))?);
+            },
+            "--fps-from" =>{
+                let value = args.next().ok_or("--fps-from requires parameter")?;
+                fps_from = Some(value.parse::<f64>().ok().filter(|x| *x>0.).ok_or(format!("invalid fps {}",value))?);
+            },
+            "--fps-to" =>{
+                let value = args.next().ok_or("--fps-to requires parameter")?;
+                fps_to = Some(value.parse::<f64>().ok().filter(|x| *x>0.).ok_or(format!("invalid fps {}",value))?);
+            },
+            "--resync" =>{
+                let value = args.next().ok_or("--resync requires parameter")?;
+                resync = Some(parse_resync(&value)?);
+            },
+            "--normalize" =>{
+                normalize = true;
+            },
+            "--min-duration" =>{
+                let value = args.next().ok_or("--min-duration requires parameter")?;
+                normalize_options.min_duration = cc::Timestamp::from_millis(value.parse::<u32>().map_err(|_| format!("invalid min duration {}",value))?.into());
+            },
+            "--min-gap" =>{
+                let value = args.next().ok_or("--min-gap requires parameter")?;
+                normalize_options.min_gap = cc::Timestamp::from_millis(value.parse::<u32>().map_err(|_| format!("invalid min gap {}",value))?.into());
+            },
+            "--merge-overlaps" =>{
+                normalize_options.merge_overlaps = true;
+            },
+            "--wrap" =>{
+                let value = args.next().ok_or("--wrap requires parameter")?;
+                wrap = Some(value.parse::<usize>().ok().filter(|x| *x>0).ok_or(format!("invalid width {}",value))?);
+            },
+            "--max-lines" =>{
+                let value = args.next().ok_or("--max-lines requires parameter")?;
+                max_lines = value.parse::<usize>().ok().filter(|x| *x>0).ok_or(format!("invalid max lines {}",value))?;
+            },
+            "--convert-zh" =>{
+                let value = args.next().ok_or("--convert-zh requires parameter")?;
+                convert_zh = Some(value.parse::<zh::ZhVariant>()?);
+            },
             "--mixed" =>{
                 mixed= true;
             },
             "--doc" =>{
                 doc = true;
             },
+            "--encoding" =>{
+                let value = args.next().ok_or("--encoding requires parameter")?;
+                encoding = value.parse::<Encoding>()?;
+            },
+            "--eol" =>{
+                let value = args.next().ok_or("--eol requires parameter")?;
+                eol = value.parse::<Eol>()?;
+            },
+            "--sidecar" =>{
+                sidecar = true;
+            },
+            "--max-cps" =>{
+                let value = args.next().ok_or("--max-cps requires parameter")?;
+                check_options.max_cps = value.parse::<f64>().ok().filter(|x| *x>0.).ok_or(format!("invalid max cps {}",value))?;
+            },
+            "check" if !check && param.is_empty() =>{
+                check = true;
+            },
             _ => {
                 param.push(value);
                 args.into_iter().for_each(|x| param.push(x));
@@ -105,9 +351,29 @@ fn parse_args(args: &mut std::env::Args)-> Result<(Config,Vec<String>),Box<dyn E
         arg=args.next();
     }
     
+    let fps_conversion = match (fps_from,fps_to){
+        (Some(from),Some(to)) => Some((from,to)),
+        (None,None) => None,
+        _ => return Err("--fps-from and --fps-to must be used together".into()),
+    };
+
+    let normalize = if normalize { Some(normalize_options) } else { None };
+    let reflow = wrap.map(|max_width| cc::ReflowOptions{max_width,max_lines});
+
     bili::init_client(proxy,headers)?;
 
-    Ok((Config{work_dir,format,doc,mixed},param))
+    Ok((Config{work_dir,format,doc,mixed,format_options,bilingual,shift,scale,fps_conversion,resync,normalize,reflow,convert_zh,check,check_options,encoding,eol,sidecar},param))
+}
+
+/// `<anchor>=<time>,<anchor>=<time>`
+fn parse_resync(string: &str)-> Result<(ResyncPoint,ResyncPoint),Box<dyn Error>>{
+    let parse_pair = |pair: &str| -> Result<ResyncPoint,Box<dyn Error>>{
+        let (anchor,target) = pair.split_once('=').ok_or(format!("expected <anchor>=<time>. but found {}",pair))?;
+        let target = target.parse::<cc::Timestamp>()?;
+        Ok((anchor.parse::<cc::Anchor>()?,target))
+    };
+    let (a,b) = string.split_once(',').ok_or("--resync requires two anchors")?;
+    Ok((parse_pair(a)?,parse_pair(b)?))
 }
 
 fn parse_range(string: &str)-> Result<lookup::Page,Box<dyn Error>>{
@@ -162,9 +428,14 @@ fn lookup_mixed_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<V
                     lookup::lookup_video_id(val,ranges)?
                 };
 
+                let mut failures = 0;
                 let subtitles : Vec<cc::CcSubtitle> = vps.into_iter()
+                    .filter_map(|vp| vp.map_err(|e| {
+                        eprintln!("{}: {}",val,e);
+                        failures += 1;
+                    }).ok())
                     .flat_map(|vp| {
-                        let mut subs = vp.subtitles;
+                        let mut subs = config.merge_bilingual(vp.subtitles);
                         for sub in subs.iter_mut(){
                             config.determine_name(sub);    
                             sub.name = format!("{}-{}",vp.p,sub.name);
@@ -175,7 +446,8 @@ fn lookup_mixed_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<V
 
                  result.push(Context {
                         dir: Some(val),
-                        subtitles: subtitles
+                        subtitles: subtitles,
+                        failures,
                  });
                 if next_value.is_some(){
                     val_opt=next_value;
@@ -190,14 +462,15 @@ fn lookup_mixed_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<V
 
             }
             if target.starts_with("ep"){
-                let mut subtitles = lookup::lookup_ep_id(&target)?;
+                let mut subtitles = config.merge_bilingual(lookup::lookup_ep_id(&target)?);
                 for sub in subtitles.iter_mut(){
                     config.determine_name(sub);    
                 }
 
                 result.push(Context {
                     dir: Some(val),
-                    subtitles:subtitles 
+                    subtitles:subtitles,
+                    failures: 0,
                 });
                 val_opt = params.next();
                 continue; 
@@ -220,12 +493,11 @@ fn lookup_mixed_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<V
             }
 
         }
-        if let Some(subtitle) = subtitle{
-            result.push(Context { 
-                dir: None , 
-                subtitles: vec![subtitle],
-            })
-        }
+        result.push(Context { 
+            dir: None , 
+            failures: if subtitle.is_none() { 1 } else { 0 },
+            subtitles: subtitle.into_iter().collect(),
+        });
 
         val_opt = params.next();
 
@@ -258,9 +530,14 @@ fn lookup_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<Vec<Con
                 lookup::lookup_video_id(arg0,ranges)?
             };
 
+            let mut failures = 0;
             let subtitles : Vec<cc::CcSubtitle> = vps.into_iter()
+                .filter_map(|vp| vp.map_err(|e| {
+                    eprintln!("{}: {}",arg0,e);
+                    failures += 1;
+                }).ok())
                 .flat_map(|vp| {
-                    let mut subs = vp.subtitles;
+                    let mut subs = config.merge_bilingual(vp.subtitles);
                     for sub in subs.iter_mut(){
                         config.determine_name(sub);    
                         sub.name = format!("{}-{}",vp.p,sub.name);
@@ -271,7 +548,8 @@ fn lookup_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<Vec<Con
             return Ok(vec![
                 Context {
                     dir: Some(arg0),
-                    subtitles: subtitles
+                    subtitles: subtitles,
+                    failures,
                 }
                 ]);
  
@@ -279,18 +557,23 @@ fn lookup_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<Vec<Con
             let mut result = vec![];
             param.iter()
                 .for_each(|target| match lookup::lookup_ep_id(&target.to_lowercase()){
-                    Ok(mut subtitles)=>{
+                    Ok(subtitles)=>{
+                        let mut subtitles = config.merge_bilingual(subtitles);
                         for sub in subtitles.iter_mut(){
                             config.determine_name(sub);    
                         }
 
                         result.push(Context {
                             dir: Some(target),
-                            subtitles: subtitles
+                            subtitles: subtitles,
+                            failures: 0,
                         });
     
                     },
-                    Err(e) => eprintln!("fail to lookup {}: {}",target,e),
+                    Err(e) => {
+                        eprintln!("fail to lookup {}: {}",target,e);
+                        result.push(Context { dir: Some(target), subtitles: vec![], failures: 1 });
+                    },
                 });
 
             return Ok(result);
@@ -299,25 +582,23 @@ fn lookup_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<Vec<Con
     }
 
     let mut result= Vec::new();
+    let mut failures = 0;
     if let Ok(_url) = Url::parse(arg0){
         param.iter()
             .map(|x|x.trim())
             .filter(|x|!x.is_empty())
-            .map(|x| {
-                match Url::parse(x){
-                    Ok(url) =>Some( url ), 
-                    Err(_)=>{
-                        eprintln!("fail to parse {}",x);
-                        None
-                    },
-                }
-            })
-            .filter(|r|r.is_some())
-            .map(|r|r.unwrap())
-            .for_each(|url|{
+            .for_each(|x|{
+                let Ok(url) = Url::parse(x) else{
+                    eprintln!("fail to parse {}",x);
+                    failures += 1;
+                    return;
+                };
                 match lookup::lookup_cc_api(&url){
                     Ok(subtitle) => result.push(subtitle),
-                    Err(e) => eprintln!("fail to lookup {}: {}",url,e),
+                    Err(e) => {
+                        eprintln!("fail to lookup {}: {}",url,e);
+                        failures += 1;
+                    },
                 }
             });
         
@@ -330,7 +611,10 @@ fn lookup_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<Vec<Con
             .for_each(|path| {
                 match lookup::lookup_file(&path){
                     Ok(subtitle) => result.push(subtitle),
-                    Err(e) => eprintln!("{}: {}",path.display(),e),
+                    Err(e) => {
+                        eprintln!("{}: {}",path.display(),e);
+                        failures += 1;
+                    },
                 }
             });
 
@@ -338,31 +622,39 @@ fn lookup_param<'a>(config: &Config, param: &'a mut Vec<String>)->Result<Vec<Con
     Ok(vec![Context { 
             dir: None , 
             subtitles: result,
+            failures,
        }])
 
 
 }
 
-fn new_formatter(config: &Config)-> Box<dyn Formatter>{
-    let format = config.format.to_lowercase() ;
-    match format.as_str() {
-        "srt"=> Box::new(cc::Srt::new()), 
-        "sub"=> Box::new(cc::Sub::new()),
-        "ass"=> Box::new(cc::Ass::new()),
-        "vtt"=> Box::new(cc::Vtt::new()),
-        other => {
-            eprintln!("unsupported subtitle format {}",other);
+/// one formatter for every format of the comma separated `-c`. repeated formats are written once.
+fn new_formatters(config: &Config,registry: &format::Registry)-> Vec<Box<dyn Formatter>>{
+    let mut names: Vec<&str> = Vec::new();
+    let mut formatters = Vec::new();
+    for name in config.format.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()){
+        let Some(entry) = registry.find(name) else{
+            eprintln!("unsupported subtitle format {}. see --list-formats",name);
             process::exit(1);
+        };
+        if !names.contains(&entry.name.as_str()){
+            names.push(&entry.name);
+            formatters.push(entry.create(&config.format_options));
         }
-
     }
+    if formatters.is_empty(){
+        eprintln!("-c requires at least one format");
+        process::exit(1);
+    }
+    formatters
 
 }
 
 fn main() {
     
     let mut args = std::env::args();
-    let (mut config,mut param) = match parse_args(&mut args){
+    let registry = format::Registry::builtin();
+    let (mut config,mut param) = match parse_args(&mut args,&registry){
         Ok((config,param))=> (config,param),
         Err(e) => {
             eprintln!("{}",e);
@@ -371,7 +663,10 @@ fn main() {
     };
 
 
-    let mut formatter = new_formatter(&config);
+    let mut formatters = new_formatters(&config,&registry);
+    let mut errors = 0;
+    // subtitles seen by check mode.
+    let mut checked = 0;
 
     if param.is_empty(){
         loop{
@@ -393,22 +688,33 @@ fn main() {
                 }
 
                 let result = if config.mixed{ lookup_mixed_param(&config,&mut param)}else{ lookup_param(&config,&mut param) };
-                let contexts = match result {
+                let mut contexts = match result {
                     Ok(v)=>v,
                     Err(e)=> {
                         eprintln!("{}",e);
                         process::exit(1);
                     }
                 };
-                contexts.iter().for_each(|context| write_context(&mut config,formatter.as_mut(),context));
+                contexts.iter_mut().for_each(|context| {
+                    errors += transform_context(&config,context);
+                    if config.check{
+                        checked += context.subtitles.len();
+                        errors += check_context(&config,context);
+                    }else{
+                        write_context(&mut config,&mut formatters,context)
+                    }
+                });
             }else if let None = r {
                 eprintln!("fail to parse input.");
+                if config.check{
+                    errors += 1;
+                }
             }
 
         }
     }else{
         let result = if config.mixed{ lookup_mixed_param(&config,&mut param)}else{ lookup_param(&config,&mut param) };
-        let contexts = match result {
+        let mut contexts = match result {
             Ok(v)=>v,
             Err(e)=> {
                 eprintln!("{}",e);
@@ -416,13 +722,68 @@ fn main() {
             }
         };
 
-        contexts.iter().for_each(|context| write_context(&mut config,formatter.as_mut(),context));
+        contexts.iter_mut().for_each(|context| {
+            errors += transform_context(&config,context);
+            if config.check{
+                checked += context.subtitles.len();
+                errors += check_context(&config,context);
+            }else{
+                write_context(&mut config,&mut formatters,context)
+            }
+        });
+    }
+
+    if config.check && checked == 0{
+        eprintln!("nothing to check");
+        process::exit(1);
+    }
+    if errors > 0{
+        process::exit(1);
     }
     
     
 }
 
-fn write_context(config: &mut Config, formatter: &mut dyn Formatter, context:&Context){
+/// transform every subtitle of context. a subtitle failing to transform is reported and dropped,
+/// returns the number of them.
+fn transform_context(config: &Config, context: &mut Context)-> usize{
+    if let Some(variant) = config.convert_zh{
+        config.convert_zh(&mut context.subtitles,variant);
+    }
+    let mut errors = 0;
+    context.subtitles.retain_mut(|sub| match config.transform(sub){
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("{}: {}",sub.name,e);
+            errors += 1;
+            false
+        },
+    });
+    errors
+}
+
+/// print the issues of every subtitle in context, returns the number of errors.
+/// an input, page or episode that failed to load counts as one.
+fn check_context(config: &Config, context: &Context)-> usize{
+    let mut errors = context.failures;
+    for subtitle in context.subtitles.iter(){
+        let name = match context.dir{
+            Some(dir) => format!("{}/{}",dir,subtitle.name),
+            None => subtitle.name.clone(),
+        };
+        let issues = subtitle.check(&config.check_options);
+        if issues.is_empty(){
+            println!("{}: ok",name);
+        }
+        for issue in issues.iter(){
+            println!("{}: cue {}: {}: {}",name,issue.cue,issue.severity,issue.message);
+        }
+        errors += issues.iter().filter(|issue| issue.severity == cc::Severity::Error).count();
+    }
+    errors
+}
+
+fn write_context(config: &mut Config, formatters: &mut [Box<dyn Formatter>], context:&Context){
     let work_dir= &mut config.work_dir;
 
     let subtitles = &context.subtitles;
@@ -445,13 +806,28 @@ fn write_context(config: &mut Config, formatter: &mut dyn Formatter, context:&Co
     for subtitle in subtitles{
 
         work_dir.push(subtitle.name.clone());
-        work_dir.set_extension(formatter.ext());
+        for formatter in formatters.iter_mut(){
+            work_dir.set_extension(formatter.ext());
+
+            let path = work_dir.as_path();
+            if let Err(e) = write_subtitle_to_file(&path,subtitle,formatter.as_mut(),config.encoding,config.eol){
+                let _ = fs::remove_file(path);
+                eprintln!("fail to write {}: {}",path.display(),e);
+                process::exit(1);
+            }
 
-        let path = work_dir.as_path();
-        write_subtitle_to_file(&path,subtitle,formatter)
-          .expect("fail to write subtitle file");
+            println!("{}",path.display());
+        }
 
-        println!("{}",path.display());
+        if let (true,Some(meta)) = (config.sidecar,&subtitle.meta){
+            work_dir.set_extension("meta.json");
+            let path = work_dir.as_path();
+            if let Err(e) = fs::File::create(path).and_then(|mut file| meta.write_json(&mut file)){
+                eprintln!("fail to write {}: {}",path.display(),e);
+                process::exit(1);
+            }
+            println!("{}",path.display());
+        }
         work_dir.pop();
 
     }
@@ -461,9 +837,11 @@ fn write_context(config: &mut Config, formatter: &mut dyn Formatter, context:&Co
      
 }
 
-fn write_subtitle_to_file(file_path: &Path,subtitle: &cc::CcSubtitle, formatter: &mut dyn cc::Formatter)-> std::io::Result<()>{
-  let mut file = fs::File::create(file_path)?;
-  formatter.write(&mut file,subtitle)?;
+fn write_subtitle_to_file(file_path: &Path,subtitle: &cc::CcSubtitle, formatter: &mut dyn cc::Formatter, encoding: Encoding, eol: Eol)-> std::io::Result<()>{
+  let mut file = io::BufWriter::new(fs::File::create(file_path)?);
+  let mut writer = encoding::EncodedWriter::new(&mut file,encoding,eol);
+  formatter.write(&mut writer,subtitle)?;
+  writer.finish()?;
   Ok(())
 }
 
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use once_cell::sync::Lazy;
+
+static ST_CHARACTERS: &str = include_str!("zh/st_characters.txt");
+static ST_PHRASES: &str = include_str!("zh/st_phrases.txt");
+static TS_PHRASES: &str = include_str!("zh/ts_phrases.txt");
+static TW_PHRASES: &str = include_str!("zh/tw_phrases.txt");
+static HK_VARIANTS: &str = include_str!("zh/hk_variants.txt");
+
+/// the first traditional form of a char is the default one.
+static S2T: Lazy<Dictionary> = Lazy::new(|| Dictionary::new(pairs(ST_CHARACTERS).chain(pairs(ST_PHRASES))));
+/// every traditional form of a char goes back to the simplified one.
+static T2S: Lazy<Dictionary> = Lazy::new(|| {
+    let chars = table(ST_CHARACTERS).flat_map(|entry| {
+        let simplified = entry[0];
+        entry.into_iter().skip(1).map(move |x| (x,simplified))
+    });
+    Dictionary::new(chars.chain(pairs(TS_PHRASES)))
+});
+static TW: Lazy<Dictionary> = Lazy::new(|| Dictionary::new(pairs(TW_PHRASES)));
+static HK: Lazy<Dictionary> = Lazy::new(|| Dictionary::new(pairs(HK_VARIANTS)));
+
+/// the chinese script a subtitle can be converted to.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ZhVariant{
+    /// simplified chinese.
+    Hans,
+    /// traditional chinese.
+    Hant,
+    /// traditional chinese with the words used in taiwan.
+    Tw,
+    /// traditional chinese with the char forms used in hong kong.
+    Hk,
+}
+
+impl ZhVariant{
+    pub fn lan(&self)-> &'static str{
+        match self{
+            ZhVariant::Hans => "zh-Hans",
+            ZhVariant::Hant => "zh-Hant",
+            ZhVariant::Tw => "zh-TW",
+            ZhVariant::Hk => "zh-HK",
+        }
+    }
+
+    pub fn lan_doc(&self)-> &'static str{
+        match self{
+            ZhVariant::Hans => "中文（简体）",
+            ZhVariant::Hant => "中文（繁體）",
+            ZhVariant::Tw => "中文（台灣）",
+            ZhVariant::Hk => "中文（香港）",
+        }
+    }
+}
+
+impl FromStr for ZhVariant{
+    type Err = String;
+
+    fn from_str(s: &str)-> Result<Self,Self::Err>{
+        match s.to_lowercase().as_str(){
+            "hans" => Ok(ZhVariant::Hans),
+            "hant" => Ok(ZhVariant::Hant),
+            "tw" => Ok(ZhVariant::Tw),
+            "hk" => Ok(ZhVariant::Hk),
+            _ => Err(format!("unknown chinese variant {}",s)),
+        }
+    }
+}
+
+/// convert `text` to `variant`, chars that are not chinese are kept as they are.
+pub fn convert(text: &str,variant: ZhVariant)-> String{
+    match variant{
+        ZhVariant::Hans => T2S.convert(text),
+        ZhVariant::Hant => S2T.convert(text),
+        ZhVariant::Tw => TW.convert(&S2T.convert(text)),
+        ZhVariant::Hk => HK.convert(&S2T.convert(text)),
+    }
+}
+
+/// a replacement table matched longest entry first.
+struct Dictionary{
+    map: HashMap<String,String>,
+    max_len: usize,
+}
+
+impl Dictionary{
+    /// later entries override earlier ones.
+    fn new<'a>(entries: impl Iterator<Item=(&'a str,&'a str)>)->Self{
+        let map: HashMap<String,String> = entries.map(|(from,to)| (from.to_string(),to.to_string())).collect();
+        let max_len = map.keys().map(|x| x.chars().count()).max().unwrap_or(0);
+        Dictionary{map,max_len}
+    }
+
+    fn convert(&self,text: &str)-> String{
+        let chars: Vec<char> = text.chars().collect();
+        let mut result = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len(){
+            let matched = (1..=self.max_len.min(chars.len()-i)).rev()
+                .find_map(|len| {
+                    let key: String = chars[i..i+len].iter().collect();
+                    self.map.get(&key).map(|to| (len,to))
+                });
+            match matched{
+                Some((len,to)) => {
+                    result.push_str(to);
+                    i += len;
+                },
+                None => {
+                    result.push(chars[i]);
+                    i += 1;
+                },
+            }
+        }
+        result
+    }
+}
+
+/// the whitespace separated entries of a data file, `#` starts a comment line.
+fn table(data: &'static str)-> impl Iterator<Item=Vec<&'static str>>{
+    data.lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(|x| x.split_whitespace().collect())
+}
+
+fn pairs(data: &'static str)-> impl Iterator<Item=(&'static str,&'static str)>{
+    table(data).filter_map(|entry| Some((entry[0],*entry.get(1)?)))
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::zh::{convert,ZhVariant};
+
+    #[test]
+    fn convert_test(){
+        assert_eq!(convert("这里的头发很干净，后来我们说了关系。",ZhVariant::Hant),"這裡的頭髮很乾淨，後來我們說了關係。");
+        assert_eq!(convert("這裡的頭髮很乾淨，後來我們說了關係。",ZhVariant::Hans),"这里的头发很干净，后来我们说了关系。");
+        assert_eq!(convert("皇后在公里外等着",ZhVariant::Hant),"皇后在公里外等著");
+        assert_eq!(convert("台风来了，松开钟表的表带",ZhVariant::Hant),"颱風來了，鬆開鐘錶的錶帶");
+        assert_eq!(convert("其余的余额，一出戏，联系我们，系鞋带",ZhVariant::Hant),"其餘的餘額，一齣戲，聯繫我們，繫鞋帶");
+        assert_eq!(convert("系统里的松树和表格",ZhVariant::Hant),"系統裡的松樹和表格");
+        assert_eq!(convert("颱風鬆開鐘錶",ZhVariant::Hans),"台风松开钟表");
+        assert_eq!(convert("著名的乾隆皇帝等著",ZhVariant::Hans),"著名的乾隆皇帝等着");
+        assert_eq!(convert("打开视频软件",ZhVariant::Tw),"打開影片軟體");
+        assert_eq!(convert("这里说的",ZhVariant::Hk),"這裏説的");
+        assert_eq!(convert("説明 hello",ZhVariant::Hans),"说明 hello");
+        assert_eq!("tw".parse::<ZhVariant>(),Ok(ZhVariant::Tw));
+        assert!("jp".parse::<ZhVariant>().is_err());
+    }
+}